@@ -0,0 +1,224 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use shoshodamon::{
+    ban2::Ban2 as Ban, book::BookBuilder, error::Error, evaluator::alpha_beta2, kifu::KifuFormat,
+    record::GameRecord, Hand,
+};
+
+const USAGE: &str = "usage: shoshodamon-book <output> <kifu file or dir>... [--max-ply <plies>] [--min-count <count>] [--depth <plies>]
+kifu files are read by their extensions, and .usi or .sfen files have a `position` command per line";
+
+struct Config {
+    output: PathBuf,
+    inputs: Vec<PathBuf>,
+    max_ply: usize,
+    min_count: usize,
+    /// Depth of the search to score moves, moves are scored by their win rates if `None`
+    depth: Option<usize>,
+}
+
+fn main() {
+    let config =
+        parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|| exit_with_usage());
+    if let Err(e) = run(&config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Parse command line arguments, `None` when they do not match the usage
+fn parse_args(args: Vec<String>) -> Option<Config> {
+    let mut positional = Vec::new();
+    let mut max_ply = 32;
+    let mut min_count = 1;
+    let mut depth = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--max-ply" | "--min-count" | "--depth" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().ok()?;
+                match args[i].as_str() {
+                    "--max-ply" => max_ply = value,
+                    "--min-count" => min_count = value,
+                    _ => depth = Some(value),
+                }
+                i += 1;
+            }
+            s if !s.starts_with("--") => positional.push(PathBuf::from(s)),
+            _ => return None,
+        }
+        i += 1;
+    }
+    if positional.len() < 2 {
+        return None;
+    }
+    let output = positional.remove(0);
+    Some(Config {
+        output,
+        inputs: positional,
+        max_ply,
+        min_count,
+        depth,
+    })
+}
+
+fn run(config: &Config) -> Result<(), Error> {
+    let mut files = Vec::new();
+    for input in &config.inputs {
+        collect_files(input, &mut files)?;
+    }
+
+    let mut builder = BookBuilder::new(config.max_ply);
+    let mut games = 0;
+    for file in &files {
+        match read_records(file) {
+            Ok(records) => {
+                games += records.len();
+                records.iter().for_each(|record| builder.add_record(record));
+            }
+            Err(e) => eprintln!("skipped {}: {}", file.display(), e),
+        }
+    }
+    println!("{} games, {} positions", games, builder.len());
+
+    let book = builder.build(config.min_count, |ban, hand| {
+        config.depth.map(|depth| (search(ban, hand, depth), depth))
+    });
+    fs::write(&config.output, book.write())?;
+    println!(
+        "wrote {} positions to {}",
+        book.len(),
+        config.output.display()
+    );
+    Ok(())
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for entry in entries {
+            collect_files(&entry, files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn read_records(path: &Path) -> Result<Vec<GameRecord>, Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let text = fs::read_to_string(path)?;
+    match extension {
+        "usi" | "sfen" => Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| match GameRecord::from_usi(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    eprintln!("skipped {} line {}: {}", path.display(), i + 1, e);
+                    None
+                }
+            })
+            .collect()),
+        _ => match KifuFormat::from_extension(extension) {
+            Some(format) => Ok(vec![format.parse(&text)?]),
+            None => Err(Error::Io(format!("unknown extension {}", extension))),
+        },
+    }
+}
+
+/// Score a move by searching the position after it, for the side to move before it
+fn search(ban: &Ban, hand: &Hand, depth: usize) -> isize {
+    let mut next = ban.clone();
+    if next.apply_hand(hand).is_err() {
+        return 0;
+    }
+    let value = match alpha_beta2(&next, Vec::new(), -50000, 50000, depth, next.turn) {
+        Some((_, value)) => value,
+        // no move for the opponent
+        None => 29999 * if ban.turn { 1 } else { -1 },
+    };
+    if ban.turn {
+        value
+    } else {
+        -value
+    }
+}
+
+#[test]
+fn arguments() {
+    let args = |line: &str| parse_args(line.split_whitespace().map(String::from).collect());
+    let config = args("book.db games --max-ply 16 more.kif --depth 3").unwrap();
+    assert_eq!(config.output, PathBuf::from("book.db"));
+    assert_eq!(
+        config.inputs,
+        vec![PathBuf::from("games"), PathBuf::from("more.kif")]
+    );
+    assert_eq!(
+        (config.max_ply, config.min_count, config.depth),
+        (16, 1, Some(3))
+    );
+    assert!(args("book.db").is_none());
+    assert!(args("book.db games --max-ply").is_none());
+    assert!(args("book.db games --max-ply x").is_none());
+    assert!(args("book.db games --unknown 1").is_none());
+}
+
+#[test]
+fn build_from_files() {
+    use shoshodamon::book::Book;
+
+    let dir = std::env::temp_dir().join(format!("shoshodamon-book-{}", process::id()));
+    let games = dir.join("games");
+    fs::create_dir_all(games.join("sub")).unwrap();
+    fs::write(
+        games.join("a.usi"),
+        "position startpos moves 7g7f 3c3d\n\nstartpos moves 7g7f 8c8d\nnot a position\n",
+    )
+    .unwrap();
+    fs::write(games.join("sub/b.csa"), "PI\n+\n+2726FU\n-8384FU\n%TORYO\n").unwrap();
+    fs::write(games.join("c.txt"), "").unwrap();
+
+    let mut files = Vec::new();
+    collect_files(&games, &mut files).unwrap();
+    assert_eq!(
+        files,
+        vec![
+            games.join("a.usi"),
+            games.join("c.txt"),
+            games.join("sub/b.csa")
+        ]
+    );
+    assert_eq!(read_records(&files[0]).unwrap().len(), 2);
+    assert!(read_records(&files[1]).is_err());
+    assert_eq!(read_records(&files[2]).unwrap()[0].moves.len(), 2);
+
+    let config = Config {
+        output: dir.join("book.db"),
+        inputs: vec![games],
+        max_ply: 1,
+        min_count: 1,
+        depth: None,
+    };
+    run(&config).unwrap();
+    let book = Book::load(&config.output).unwrap();
+    assert_eq!(book.len(), 1);
+    assert_eq!(book.moves(&Ban::new()).len(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,120 @@
+use std::{fs, path::PathBuf, process};
+
+use shoshodamon::{
+    ban2::Ban2 as Ban,
+    csa_client::{Action, Clock, CsaClient, Outcome},
+    error::Error,
+    evaluator::alpha_beta2,
+    kifu::csa,
+    mate::mate_in_one,
+};
+
+const USAGE: &str = "usage: shoshodamon-csa <host:port> <name> <password> [--games <count>] [--depth <plies>] [--record <dir>]";
+
+struct Config {
+    addr: String,
+    name: String,
+    password: String,
+    games: usize,
+    depth: usize,
+    record: Option<PathBuf>,
+}
+
+fn main() {
+    let config = parse_args(std::env::args().skip(1).collect());
+    if let Err(e) = run(&config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn parse_args(args: Vec<String>) -> Config {
+    let mut positional = Vec::new();
+    let mut games = 1;
+    let mut depth = 3;
+    let mut record = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--games" | "--depth" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().unwrap_or_else(|_| exit_with_usage());
+                if args[i] == "--games" {
+                    games = value;
+                } else {
+                    depth = value;
+                }
+                i += 1;
+            }
+            "--record" if i + 1 < args.len() => {
+                record = Some(PathBuf::from(&args[i + 1]));
+                i += 1;
+            }
+            s if !s.starts_with("--") => positional.push(s.to_string()),
+            _ => exit_with_usage(),
+        }
+        i += 1;
+    }
+    if positional.len() != 3 {
+        exit_with_usage();
+    }
+    let mut positional = positional.into_iter();
+    Config {
+        addr: positional.next().unwrap(),
+        name: positional.next().unwrap(),
+        password: positional.next().unwrap(),
+        games,
+        depth,
+        record,
+    }
+}
+
+fn run(config: &Config) -> Result<(), Error> {
+    let mut client = CsaClient::connect(config.addr.as_str())?;
+    client.login(&config.name, &config.password)?;
+
+    let mut played = 0;
+    while played < config.games {
+        let summary = client.wait_game()?;
+        println!(
+            "game {}: {} vs {}",
+            summary.game_id, summary.sente_name, summary.gote_name
+        );
+        if !client.agree(&summary)? {
+            println!("rejected");
+            continue;
+        }
+        let game = client.play(&summary, |ban, clock| think(ban, clock, config.depth))?;
+        let outcome = match game.outcome {
+            Outcome::Win => "win",
+            Outcome::Lose => "lose",
+            Outcome::Draw => "draw",
+            Outcome::Censored => "censored",
+            Outcome::Interrupted => "interrupted",
+        };
+        println!("{} in {} moves", outcome, game.record.moves.len());
+        if let Some(dir) = &config.record {
+            let path = dir.join(format!("{}.csa", summary.game_id));
+            fs::write(&path, csa::write(&game.record))?;
+        }
+        played += 1;
+    }
+    client.logout()
+}
+
+fn think(ban: &Ban, _: &Clock, depth: usize) -> Action {
+    if let Some(hand) = mate_in_one(ban) {
+        return Action::Move(hand);
+    }
+    match alpha_beta2(ban, Vec::new(), -50000, 50000, depth, ban.turn) {
+        Some((hands, _)) if !hands.is_empty() => Action::Move(hands[0].clone()),
+        _ => match ban.get_possibility_bans(ban.turn).into_iter().next() {
+            Some((_, hand)) => Action::Move(hand),
+            None => Action::Resign,
+        },
+    }
+}
@@ -0,0 +1,264 @@
+use std::time::{Duration, Instant};
+
+use shoshodamon::{
+    csa_client::Action,
+    error::Error,
+    evaluator::alpha_beta2,
+    mate::mate_in_one,
+    record::{GameRecord, GameResult, MoveRecord},
+    rules,
+};
+
+/// Allowance for the communication with engines, added to their times
+pub const TIME_MARGIN: Duration = Duration::from_millis(200);
+
+/// Time control of both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    pub total: Duration,
+    pub byoyomi: Duration,
+    pub increment: Duration,
+}
+
+/// Remaining times of both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// Remaining times of (gote, sente) not including byoyomi
+    pub remaining: [Duration; 2],
+    pub byoyomi: Duration,
+    pub increment: Duration,
+}
+
+impl Clock {
+    pub fn new(time: &TimeControl) -> Self {
+        Self {
+            remaining: [time.total; 2],
+            byoyomi: time.byoyomi,
+            increment: time.increment,
+        }
+    }
+
+    /// Time which given turn can use for its move
+    pub fn limit(&self, turn: bool) -> Duration {
+        self.remaining[turn as usize] + self.byoyomi
+    }
+
+    /// Consume the time of a move by given turn, `false` when it ran out of time
+    fn consume(&mut self, turn: bool, time: Duration) -> bool {
+        if time > self.limit(turn) + TIME_MARGIN {
+            return false;
+        }
+        let remaining = &mut self.remaining[turn as usize];
+        *remaining = remaining.saturating_sub(time) + self.increment;
+        true
+    }
+}
+
+pub trait Player {
+    fn name(&self) -> &str;
+
+    fn new_game(&mut self) -> Result<(), Error>;
+
+    /// Decide the action after the moves of `record`, `None` when the player ran out of time
+    fn think(&mut self, record: &GameRecord, clock: &Clock) -> Result<Option<Action>, Error>;
+
+    /// Notify the end of a game, `None` for a draw
+    fn game_over(&mut self, _win: Option<bool>) {}
+}
+
+/// The built-in engine searching to the fixed depth
+pub struct Builtin {
+    name: String,
+    depth: usize,
+}
+
+impl Builtin {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            name: format!("depth:{}", depth),
+            depth,
+        }
+    }
+}
+
+impl Player for Builtin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn think(&mut self, record: &GameRecord, _: &Clock) -> Result<Option<Action>, Error> {
+        let ban = record.last_ban()?;
+        if rules::can_declare(&ban) {
+            return Ok(Some(Action::Declare));
+        }
+        if let Some(hand) = mate_in_one(&ban) {
+            return Ok(Some(Action::Move(hand)));
+        }
+        let action = match alpha_beta2(&ban, Vec::new(), -50000, 50000, self.depth, ban.turn) {
+            Some((hands, _)) if !hands.is_empty() => Action::Move(hands[0].clone()),
+            _ => Action::Resign,
+        };
+        Ok(Some(action))
+    }
+}
+
+/// Rules of games other than time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameRule {
+    pub time: TimeControl,
+    /// Games reaching this number of moves, including the ones of the opening, are draws
+    pub max_moves: usize,
+}
+
+/// Play a game from `opening` between `players` as (gote, sente), and return its record with
+/// the result
+pub fn play(
+    players: [&mut dyn Player; 2],
+    opening: &GameRecord,
+    rule: &GameRule,
+) -> Result<GameRecord, Error> {
+    let [gote, sente] = players;
+    gote.new_game()?;
+    sente.new_game()?;
+    let mut record = opening.clone();
+    record.result = None;
+    let mut bans = vec![record.initial.clone()];
+    for mv in &record.moves {
+        let mut next = bans.last().unwrap().clone();
+        next.apply_hand(&mv.hand)?;
+        bans.push(next);
+    }
+    let mut clock = Clock::new(&rule.time);
+
+    let result = loop {
+        let ban = bans.last().unwrap().clone();
+        let turn = ban.turn;
+        if record.moves.len() >= rule.max_moves {
+            break GameResult::Jishogi;
+        }
+        if ban.is_check_mate(turn) {
+            break GameResult::Checkmate;
+        }
+        let player: &mut dyn Player = if turn { &mut *sente } else { &mut *gote };
+        let start = Instant::now();
+        let action = match player.think(&record, &clock) {
+            Ok(action) => action,
+            Err(e) => {
+                eprintln!("{}: {}", player.name(), e);
+                break GameResult::IllegalMove;
+            }
+        };
+        let elapsed = start.elapsed();
+        let action = match action {
+            Some(action) if clock.consume(turn, elapsed) => action,
+            _ => break GameResult::Timeout,
+        };
+        match action {
+            Action::Resign => break GameResult::Resign,
+            Action::Declare if rules::can_declare(&ban) => break GameResult::Declaration,
+            Action::Declare => break GameResult::IllegalMove,
+            Action::Move(hand) => {
+                let mut next = ban.clone();
+                if next.apply_hand(&hand).is_err() || next.is_check(turn) {
+                    break GameResult::IllegalMove;
+                }
+                let mut mv = MoveRecord::new(hand);
+                mv.time = Some(elapsed);
+                record.moves.push(mv);
+                bans.push(next);
+                if let Some(result) = rules::repetition(&bans) {
+                    break result;
+                }
+            }
+        }
+    };
+    record.result = Some(result);
+    let winner = record.winner();
+    sente.game_over(winner);
+    gote.game_over(winner.map(|w| !w));
+    Ok(record)
+}
+
+#[test]
+fn play_game() {
+    use shoshodamon::ban2::Ban2 as Ban;
+    use std::convert::TryInto;
+
+    struct Scripted {
+        moves: Vec<Action>,
+    }
+
+    impl Player for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn new_game(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn think(&mut self, _: &GameRecord, _: &Clock) -> Result<Option<Action>, Error> {
+            Ok(Some(self.moves.remove(0)))
+        }
+    }
+
+    let rule = GameRule {
+        time: TimeControl {
+            total: Duration::from_secs(10),
+            byoyomi: Duration::ZERO,
+            increment: Duration::ZERO,
+        },
+        max_moves: 256,
+    };
+    let scripted = |moves: &[&str]| Scripted {
+        moves: moves
+            .iter()
+            .map(|mv| match *mv {
+                "resign" => Action::Resign,
+                "win" => Action::Declare,
+                mv => Action::Move(mv.try_into().unwrap()),
+            })
+            .collect(),
+    };
+    let opening = GameRecord::new(Ban::new());
+
+    let mut sente = scripted(&["7g7f", "8h2b+"]);
+    let mut gote = scripted(&["3c3d", "resign"]);
+    let record = play([&mut gote, &mut sente], &opening, &rule).unwrap();
+    assert_eq!(record.result, Some(GameResult::Resign));
+    assert_eq!(record.winner(), Some(true));
+    assert_eq!(record.moves.len(), 3);
+
+    // the move leaves the king in check
+    let opening = GameRecord::from_usi("sfen 4k4/9/9/9/9/9/4r4/9/4K4 b - 1").unwrap();
+    let mut sente = scripted(&["5i5h"]);
+    let mut gote = scripted(&[]);
+    let record = play([&mut gote, &mut sente], &opening, &rule).unwrap();
+    assert_eq!(record.result, Some(GameResult::IllegalMove));
+    assert_eq!(record.winner(), Some(false));
+
+    let mut sente = scripted(&["win"]);
+    let record = play([&mut gote, &mut sente], &opening, &rule).unwrap();
+    assert_eq!(record.result, Some(GameResult::IllegalMove));
+
+    let mut sente = scripted(&["5i4i", "4i5i"].repeat(3));
+    let mut gote = scripted(&["5a4a", "4a5a"].repeat(3));
+    let opening = GameRecord::from_usi("sfen 4k4/9/9/9/9/9/9/9/4K4 b - 1").unwrap();
+    let record = play([&mut gote, &mut sente], &opening, &rule).unwrap();
+    assert_eq!(record.result, Some(GameResult::Sennichite));
+    assert_eq!(record.moves.len(), 12);
+    assert_eq!(record.winner(), None);
+
+    let rule = GameRule {
+        max_moves: 2,
+        ..rule
+    };
+    let mut sente = scripted(&["7g7f"]);
+    let mut gote = scripted(&["3c3d"]);
+    let record = play([&mut gote, &mut sente], &GameRecord::new(Ban::new()), &rule).unwrap();
+    assert_eq!(record.result, Some(GameResult::Jishogi));
+}
@@ -0,0 +1,318 @@
+mod game;
+mod usi;
+
+use std::{fs, path::PathBuf, process, time::Duration};
+
+use shoshodamon::{
+    ban2::Ban2 as Ban,
+    elo::{MatchScore, Sprt, SprtResult},
+    error::Error,
+    kifu::csa,
+    record::{GameRecord, GOTE_NAME, SENTE_NAME},
+};
+
+use game::{Builtin, GameRule, Player, TimeControl};
+
+const USAGE: &str = "usage: shoshodamon-match <engine1> <engine2> [--games <count>] [--openings <file>] [--time <ms>] [--byoyomi <ms>] [--inc <ms>] [--max-moves <plies>] [--sprt <elo0>,<elo1>] [--alpha <a>] [--beta <b>] [--option1 <name>=<value>]... [--option2 <name>=<value>]... [--record <dir>]
+an engine is `depth:<plies>` for the built-in one or a path of an USI executable
+openings have a SFEN or a `position` command per line, and each of them is played twice with colours swapped";
+
+struct Config {
+    engines: [String; 2],
+    /// USI options of each engine
+    options: [Vec<(String, String)>; 2],
+    games: usize,
+    openings: Option<PathBuf>,
+    rule: GameRule,
+    sprt: Option<Sprt>,
+    record: Option<PathBuf>,
+}
+
+fn main() {
+    let config =
+        parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|| exit_with_usage());
+    if let Err(e) = run(&config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Parse command line arguments, `None` when they do not match the usage
+fn parse_args(args: Vec<String>) -> Option<Config> {
+    let mut positional = Vec::new();
+    let mut options = [Vec::new(), Vec::new()];
+    let mut games = 2;
+    let mut openings = None;
+    let mut time = TimeControl {
+        total: Duration::ZERO,
+        byoyomi: Duration::from_secs(1),
+        increment: Duration::ZERO,
+    };
+    let mut max_moves = 256;
+    let mut sprt: Option<Sprt> = None;
+    let (mut alpha, mut beta) = (0.05, 0.05);
+    let mut record = None;
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1).map(String::as_str);
+        match (args[i].as_str(), value) {
+            ("--games", Some(v)) | ("--max-moves", Some(v)) => {
+                let v = v.parse().ok()?;
+                if args[i] == "--games" {
+                    games = v;
+                } else {
+                    max_moves = v;
+                }
+                i += 1;
+            }
+            ("--time", Some(v)) | ("--byoyomi", Some(v)) | ("--inc", Some(v)) => {
+                let v = Duration::from_millis(v.parse().ok()?);
+                match args[i].as_str() {
+                    "--time" => time.total = v,
+                    "--byoyomi" => time.byoyomi = v,
+                    _ => time.increment = v,
+                }
+                i += 1;
+            }
+            ("--sprt", Some(v)) => {
+                let (elo0, elo1) = v
+                    .split_once(',')
+                    .and_then(|(a, b)| Some((a.parse().ok()?, b.parse().ok()?)))?;
+                sprt = Some(Sprt::new(elo0, elo1));
+                i += 1;
+            }
+            ("--alpha", Some(v)) | ("--beta", Some(v)) => {
+                let v = v.parse().ok()?;
+                if args[i] == "--alpha" {
+                    alpha = v;
+                } else {
+                    beta = v;
+                }
+                i += 1;
+            }
+            ("--option1", Some(v)) | ("--option2", Some(v)) => {
+                let (name, value) = v.split_once('=')?;
+                let engine = (args[i] == "--option2") as usize;
+                options[engine].push((name.to_string(), value.to_string()));
+                i += 1;
+            }
+            ("--openings", Some(v)) => {
+                openings = Some(PathBuf::from(v));
+                i += 1;
+            }
+            ("--record", Some(v)) => {
+                record = Some(PathBuf::from(v));
+                i += 1;
+            }
+            (s, _) if !s.starts_with("--") => positional.push(s.to_string()),
+            _ => return None,
+        }
+        i += 1;
+    }
+    let no_time = time.total.is_zero() && time.byoyomi.is_zero() && time.increment.is_zero();
+    if positional.len() != 2 || no_time {
+        return None;
+    }
+    let mut positional = positional.into_iter();
+    Some(Config {
+        engines: [positional.next().unwrap(), positional.next().unwrap()],
+        options,
+        games,
+        openings,
+        rule: GameRule { time, max_moves },
+        sprt: sprt.map(|sprt| Sprt {
+            alpha,
+            beta,
+            ..sprt
+        }),
+        record,
+    })
+}
+
+fn run(config: &Config) -> Result<(), Error> {
+    let openings = match &config.openings {
+        Some(path) => read_openings(&fs::read_to_string(path)?)?,
+        None => vec![GameRecord::new(Ban::new())],
+    };
+    let mut engines = [
+        launch(&config.engines[0], &config.options[0])?,
+        launch(&config.engines[1], &config.options[1])?,
+    ];
+    let names = [engines[0].name().to_string(), engines[1].name().to_string()];
+
+    let mut score = MatchScore::new();
+    for n in 0..config.games {
+        let opening = &openings[n / 2 % openings.len()];
+        // the first engine plays sente in even games
+        let first_sente = n % 2 == 0;
+        let [first, second] = &mut engines;
+        let players: [&mut dyn Player; 2] = if first_sente {
+            [second.as_mut(), first.as_mut()]
+        } else {
+            [first.as_mut(), second.as_mut()]
+        };
+        let mut record = game::play(players, opening, &config.rule)?;
+
+        let sente = &names[!first_sente as usize];
+        let gote = &names[first_sente as usize];
+        record.set_header(SENTE_NAME, sente);
+        record.set_header(GOTE_NAME, gote);
+        score.add(record.winner().map(|winner| winner == first_sente));
+        println!(
+            "game {}: {} vs {}, {:?} in {} moves",
+            n + 1,
+            sente,
+            gote,
+            record.result.unwrap(),
+            record.moves.len()
+        );
+        if let Some(dir) = &config.record {
+            fs::write(dir.join(format!("{:04}.csa", n + 1)), csa::write(&record))?;
+        }
+
+        print_score(&names, &score);
+        if let Some(sprt) = &config.sprt {
+            let (lower, upper) = sprt.bounds();
+            let result = sprt.test(&score);
+            println!(
+                "SPRT elo0 {} elo1 {}: LLR {:.2} [{:.2}, {:.2}]{}",
+                sprt.elo0,
+                sprt.elo1,
+                sprt.llr(&score),
+                lower,
+                upper,
+                match result {
+                    SprtResult::AcceptH0 => ", H0 accepted",
+                    SprtResult::AcceptH1 => ", H1 accepted",
+                    SprtResult::Continue => "",
+                }
+            );
+            if result != SprtResult::Continue {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn launch(engine: &str, options: &[(String, String)]) -> Result<Box<dyn Player>, Error> {
+    match engine.strip_prefix("depth:") {
+        Some(depth) => {
+            let depth = depth
+                .parse()
+                .map_err(|_| Error::Io(format!("invalid engine {}", engine)))?;
+            Ok(Box::new(Builtin::new(depth)))
+        }
+        None => Ok(Box::new(usi::launch(engine, options)?)),
+    }
+}
+
+/// Read openings written as SFENs or `position` commands, ignoring empty lines and comments
+fn read_openings(text: &str) -> Result<Vec<GameRecord>, Error> {
+    let openings = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            if line.starts_with("position") || line.starts_with("sfen") || line == "startpos" {
+                GameRecord::from_usi(line)
+            } else {
+                Ok(GameRecord::new(Ban::from_sfen_validated(line)?))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if openings.is_empty() {
+        return Err(Error::Io("no openings".to_string()));
+    }
+    Ok(openings)
+}
+
+fn print_score(names: &[String; 2], score: &MatchScore) {
+    let elo = match score.elo() {
+        Some((elo, error)) => format!("{:.1} ± {:.1}", elo, error),
+        None => "-".to_string(),
+    };
+    println!(
+        "score of {} vs {}: {} - {} - {} [{:.3}] {} games, Elo {}",
+        names[0],
+        names[1],
+        score.wins,
+        score.losses,
+        score.draws,
+        score.score(),
+        score.games(),
+        elo
+    );
+}
+
+#[test]
+fn arguments() {
+    let args = |line: &str| parse_args(line.split_whitespace().map(String::from).collect());
+    let config = args(
+        "depth:1 ./engine --games 10 --time 60000 --byoyomi 0 --inc 500 --sprt 0,10 --alpha 0.1 \
+         --option2 USI_Hash=64 --option2 Threads=2 --option1 BookFile=",
+    )
+    .unwrap();
+    assert_eq!(
+        config.engines,
+        ["depth:1".to_string(), "./engine".to_string()]
+    );
+    assert_eq!(config.games, 10);
+    assert_eq!(config.rule.time.total, Duration::from_secs(60));
+    assert_eq!(config.rule.time.byoyomi, Duration::ZERO);
+    assert_eq!(config.rule.time.increment, Duration::from_millis(500));
+    assert_eq!(config.rule.max_moves, 256);
+    let sprt = config.sprt.unwrap();
+    assert_eq!(
+        (sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta),
+        (0.0, 10.0, 0.1, 0.05)
+    );
+    assert_eq!(
+        config.options[0],
+        vec![("BookFile".to_string(), String::new())]
+    );
+    assert_eq!(config.options[1].len(), 2);
+
+    let config = args("a b").unwrap();
+    assert_eq!(config.games, 2);
+    assert_eq!(config.rule.time.byoyomi, Duration::from_secs(1));
+    assert!(config.sprt.is_none());
+
+    assert!(args("a").is_none());
+    assert!(args("a b c").is_none());
+    assert!(args("a b --byoyomi 0").is_none());
+    assert!(args("a b --games").is_none());
+    assert!(args("a b --games x").is_none());
+    assert!(args("a b --sprt 10").is_none());
+    assert!(args("a b --option1 Hash").is_none());
+    assert!(args("a b --unknown 1").is_none());
+}
+
+#[test]
+fn openings() {
+    let openings = read_openings(
+        "# comment
+lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2
+
+position startpos moves 7g7f 3c3d
+startpos
+sfen 4k4/9/9/9/9/9/9/9/4K4 b G 1 moves G*5b
+",
+    )
+    .unwrap();
+    assert_eq!(openings.len(), 4);
+    assert!(!openings[0].initial.turn);
+    assert_eq!(openings[1].moves.len(), 2);
+    assert_eq!(openings[2], GameRecord::new(Ban::new()));
+    assert_eq!(openings[3].moves.len(), 1);
+
+    assert!(read_openings("# only a comment\n").is_err());
+    assert!(read_openings("startpos moves 7g7e\n").is_err());
+    // sfens of openings are validated
+    assert!(read_openings("4k3P/9/9/9/9/9/9/9/4K4 b - 1\n").is_err());
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use shoshodamon::{
+    csa_client::Action,
+    error::Error,
+    record::GameRecord,
+    usi::{BestMove, GoParams},
+    usi_engine::UsiEngine,
+};
+
+use crate::game::{Clock, Player, TIME_MARGIN};
+
+/// Launch given executable and set `options` as (name, value)
+pub fn launch(path: &str, options: &[(String, String)]) -> Result<UsiEngine, Error> {
+    let mut engine = UsiEngine::spawn(path, &[])?;
+    for (name, value) in options {
+        engine.set_option(name, value)?;
+    }
+    Ok(engine)
+}
+
+impl Player for UsiEngine {
+    fn name(&self) -> &str {
+        UsiEngine::name(self)
+    }
+
+    fn new_game(&mut self) -> Result<(), Error> {
+        self.is_ready()?;
+        UsiEngine::new_game(self)
+    }
+
+    fn think(&mut self, record: &GameRecord, clock: &Clock) -> Result<Option<Action>, Error> {
+        self.position(record)?;
+        let params = if clock.increment > Duration::ZERO {
+            GoParams {
+                inc: Some((clock.increment, clock.increment)),
+                ..GoParams::default()
+            }
+        } else {
+            GoParams {
+                byoyomi: Some(clock.byoyomi),
+                ..GoParams::default()
+            }
+        };
+        let params = GoParams {
+            btime: clock.remaining[1],
+            wtime: clock.remaining[0],
+            ..params
+        };
+        let timeout = clock.limit(record.last_turn()) + TIME_MARGIN;
+        Ok(self.go(&params, timeout)?.map(|result| match result.best {
+            BestMove::Move { hand, .. } => Action::Move(hand),
+            BestMove::Resign => Action::Resign,
+            BestMove::Win => Action::Declare,
+        }))
+    }
+
+    fn game_over(&mut self, win: Option<bool>) {
+        let _ = UsiEngine::game_over(self, win);
+    }
+}
@@ -1,12 +1,149 @@
-use std::convert::TryInto;
+use std::{
+    io::BufRead,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
 use rand::{thread_rng, Rng};
 use shoshodamon::{
     ban2::Ban2 as Ban,
+    book::{Book, Selection},
+    error::Error,
     evaluator::{self, alpha_beta2},
-    Hand,
+    mate::{mate_in_one, solve_mate, MateResult},
+    record::GameRecord,
+    time_manager::TimeManager,
+    usi::{options::UsiOptions, BestMove, Checkmate, Command, GoParams, Info, Response, Score},
 };
 
+/// Limits of `go mate`
+const MATE_DEPTH: usize = 15;
+const MATE_NODES: usize = 1_000_000;
+/// Deepest iteration of the search
+const MAX_DEPTH: usize = 5;
+/// Time of an iteration compared with the previous one. The search does not order moves, so
+/// it grows with about the number of legal moves. This only avoids starting iterations which
+/// cannot finish, as the search is stopped at the maximum time anyway
+const ITERATION_GROWTH: u32 = 80;
+
+/// Options sent in reply to `usi`. The search has no hash table, threads, multiple PVs or
+/// evaluation file, so USI_Hash, Threads, MultiPV and EvalFile are not declared. GUIs still
+/// send USI_Hash, which the registry accepts like any reserved option
+fn declare_options() -> UsiOptions {
+    UsiOptions::new()
+        .check("USI_Ponder", false)
+        .filename("BookFile", "")
+        .spin("BookVariety", 0, 0, 10000)
+        // milliseconds kept from the clock for the network and the GUI
+        .spin("ByoyomiMargin", 100, 0, 10000)
+}
+
+/// A search running on another thread, which prints `bestmove` or `checkmate` when it ends
+struct Searching {
+    handle: JoinHandle<()>,
+    /// Set by `stop` and `ponderhit` to let `go infinite` and `go ponder` send `bestmove`
+    released: Arc<AtomicBool>,
+    /// Maximum time after `ponderhit` of `go ponder` with clocks
+    ponder_limit: Option<Duration>,
+}
+
+impl Searching {
+    /// Start searching `ban` unless `preset` is already chosen
+    fn start(
+        ban: Option<Ban>,
+        preset: Option<BestMove>,
+        params: GoParams,
+        margin: Duration,
+    ) -> Self {
+        let turn = ban.as_ref().is_none_or(|ban| ban.turn);
+        let ply = ban.as_ref().map_or(1, |ban| ban.ply);
+        let mut tm = TimeManager::new(&params, turn, ply, margin);
+        let ponder_limit = if params.ponder {
+            let params = GoParams {
+                ponder: false,
+                ..params
+            };
+            TimeManager::new(&params, turn, ply, margin).maximum()
+        } else {
+            None
+        };
+        // USI forbids bestmove of these searches before stop or ponderhit
+        let hold = params.infinite || params.ponder;
+        Self::spawn(tm.maximum(), hold, ponder_limit, move || {
+            let best = match (preset, ban) {
+                (Some(best), _) => best,
+                (None, Some(ban)) => search(&ban, &mut tm),
+                (None, None) => BestMove::Resign,
+            };
+            Response::BestMove(best)
+        })
+    }
+
+    /// Start searching a checkmate of `ban` within `limit`, answering `checkmate timeout` when
+    /// it is reached or the search is stopped
+    fn mate(ban: Option<Ban>, limit: Option<Duration>) -> Self {
+        Self::spawn(limit, false, None, move || {
+            let checkmate = match ban.map(|ban| solve_mate(&ban, MATE_DEPTH, MATE_NODES)) {
+                Some(MateResult::Mate(hands)) => Checkmate::Mate(hands),
+                Some(MateResult::NoMate) | None => Checkmate::NoMate,
+                Some(MateResult::Unknown) => Checkmate::Timeout,
+            };
+            Response::Checkmate(checkmate)
+        })
+    }
+
+    /// Run `search` until `limit` and print its response, waiting for `stop` or `ponderhit`
+    /// before it when `hold` is set
+    fn spawn<F>(
+        limit: Option<Duration>,
+        hold: bool,
+        ponder_limit: Option<Duration>,
+        search: F,
+    ) -> Self
+    where
+        F: FnOnce() -> Response + Send + 'static,
+    {
+        evaluator::STOP.store(false, Ordering::Relaxed);
+        *evaluator::DEADLINE.lock().unwrap() = limit.map(|limit| Instant::now() + limit);
+
+        let released = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let released = released.clone();
+            thread::spawn(move || {
+                let response = search();
+                while hold && !released.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                println!("{}", response);
+            })
+        };
+        Self {
+            handle,
+            released,
+            ponder_limit,
+        }
+    }
+
+    /// Switch from pondering to thinking within the clock
+    fn ponderhit(&self) {
+        if let Some(limit) = self.ponder_limit {
+            *evaluator::DEADLINE.lock().unwrap() = Some(Instant::now() + limit);
+        }
+        self.released.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop the search and wait for its `bestmove` or `checkmate`
+    fn stop(self) {
+        evaluator::STOP.store(true, Ordering::Relaxed);
+        self.released.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
 fn main() {
     // let ban =
     //     Ban::from_sfen("lnsg2k2/6G2/ppp6/3p+R4/9/9/PPPPPPP1P/1B7/LNSGKGSNL w RBNL6Ps 1").unwrap();
@@ -22,107 +159,183 @@ fn main() {
     //     );
     // }
 
-    let mut current_ban = None;
-    loop {
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
-
-        let args = input.split_ascii_whitespace().collect::<Vec<_>>();
-        match args[0] {
-            "usi" => {
-                println!("id name Shoshodamon v0.0.1");
-                println!("id author MysteryJump");
-                println!("usiok");
+    let mut current_game: Option<GameRecord> = None;
+    let mut options = declare_options();
+    // the book with the file it was loaded from
+    let mut book: Option<(String, Book)> = None;
+    let mut searching: Option<Searching> = None;
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let command = match Command::parse(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                if line.trim_start().starts_with("position") {
+                    current_game = None;
+                }
+                println!("info string {}", e);
+                continue;
             }
-            "setoption" => {}
-            "usinewgame" => {}
-            "isready" => {
-                println!("readyok");
+        };
+        // only these commands are expected during a search
+        if !matches!(command, Command::IsReady | Command::PonderHit) {
+            if let Some(searching) = searching.take() {
+                searching.stop();
             }
-            "position" => {
-                let mut args = &args[1..];
-                let sp = if args[0] == "startpos" {
-                    args = &args[1..];
-                    shoshodamon::START_POS.to_string()
-                } else if args[0] == "sfen" {
-                    args = &args[1..];
-                    let sfen = format!("{} {} {} {}", args[0], args[1], args[2], args[3]);
-                    args = &args[4..];
-                    sfen
-                } else {
-                    panic!();
-                };
-                let mut ban = Ban::from_sfen(&sp).unwrap();
-                if args.is_empty() {
-                    current_ban = Some(ban);
-                    continue;
+        }
+        match command {
+            Command::Usi => {
+                println!("{}", Response::IdName("Shoshodamon v0.0.1".to_string()));
+                println!("{}", Response::IdAuthor("MysteryJump".to_string()));
+                for option in options.defs() {
+                    println!("{}", Response::Option(option.clone()));
                 }
-                if args[0] != "moves" {
-                    panic!();
-                } else {
-                    args = &args[1..];
+                println!("{}", Response::UsiOk);
+            }
+            Command::SetOption { name, value } => {
+                if let Err(e) = options.set(&name, value.as_deref()) {
+                    println!("info string {}", e);
                 }
-
-                for mv in args {
-                    let hand = (*mv).try_into().unwrap();
-                    match hand {
-                        Hand::Movement {
-                            x,
-                            y,
-                            dx,
-                            dy,
-                            with_promote,
-                        } => ban
-                            .move_piece(
-                                x,
-                                y,
-                                (x as isize + dx) as usize,
-                                (y as isize + dy) as usize,
-                                with_promote,
-                            )
-                            .unwrap(),
-                        Hand::Putting { piece, x, y } => ban.put_piece(piece, x, y).unwrap(),
+            }
+            Command::UsiNewGame => {}
+            Command::IsReady => {
+                let book_file = options.get_text("BookFile").unwrap_or_default();
+                if book.as_ref().map(|(file, _)| file.as_str()) != Some(book_file) {
+                    book = None;
+                    if !book_file.is_empty() {
+                        match Book::load(book_file) {
+                            Ok(loaded) => book = Some((book_file.to_string(), loaded)),
+                            Err(e) => println!("info string Cannot load book: {}", e),
+                        }
                     }
                 }
-                current_ban = Some(ban);
+                println!("{}", Response::ReadyOk);
             }
-            "go" => {
-                if let Some(ban) = current_ban.clone() {
-                    // let depth = 1000000;
-                    let result = alpha_beta2(&ban, Vec::new(), -50000, 50000, 5, true); // eval(&ban, depth);
-                    let depth = evaluator::COUNT.load(std::sync::atomic::Ordering::Relaxed);
-                    evaluator::COUNT.store(0, std::sync::atomic::Ordering::Release);
-                    if let Some(r) = result {
-                        let hand = &r.0[0];
-                        println!(
-                            "info depth {} nodes {} score cp {} pv {}",
-                            r.0.len(),
-                            depth,
-                            r.1,
-                            r.0.iter()
-                                .map(|x| String::from(x.clone()))
-                                .collect::<Vec<_>>()
-                                .join(" ")
-                        );
-                        println!("bestmove {}", String::from(hand.clone()))
-                    } else {
-                        let bans = ban.get_possibility_bans(ban.turn);
-                        if bans.is_empty() {
-                            println!("bestmove resign")
-                        } else {
-                            let ran = thread_rng().gen_range(0..bans.len());
-                            let hand = bans[ran].1.clone();
-                            println!("bestmove {}", String::from(hand));
-                        }
+            Command::Position(record) => {
+                current_game = match record.initial.validate() {
+                    Ok(()) => Some(*record),
+                    Err(violations) => {
+                        println!("info string {}", Error::InvalidPosition(violations));
+                        None
                     }
-                } else {
-                    panic!()
+                };
+            }
+            Command::GoMate(limit) => {
+                let ban = current_game.as_ref().and_then(|g| g.last_ban().ok());
+                if ban.is_none() {
+                    println!("info string No valid position is given");
                 }
+                searching = Some(Searching::mate(ban, limit));
+            }
+            Command::Go(params) => {
+                let ban = current_game.as_ref().and_then(|g| g.last_ban().ok());
+                let preset = match &ban {
+                    Some(ban) => {
+                        let selection = match options.get_spin("BookVariety") {
+                            Some(margin) if margin > 0 => Selection::Weighted {
+                                margin: margin as _,
+                            },
+                            _ => Selection::Best,
+                        };
+                        book.as_ref()
+                            .and_then(|(_, book)| book.probe(ban, selection, &mut thread_rng()))
+                            .map(|mv| {
+                                println!("info string book move");
+                                BestMove::Move {
+                                    hand: mv.hand.clone(),
+                                    ponder: mv.ponder.clone(),
+                                }
+                            })
+                    }
+                    None => {
+                        println!("info string No valid position is given");
+                        Some(BestMove::Resign)
+                    }
+                };
+                let margin = options.get_spin("ByoyomiMargin").unwrap_or(0);
+                let margin = Duration::from_millis(margin as u64);
+                searching = Some(Searching::start(ban, preset, params, margin));
             }
-            "gameover" => {
-                current_ban = None;
+            Command::PonderHit => {
+                if let Some(searching) = &searching {
+                    searching.ponderhit();
+                }
+            }
+            // the search was stopped above
+            Command::Stop => {}
+            Command::GameOver(_) => {
+                current_game = None;
+            }
+            Command::Quit => break,
+        }
+    }
+    if let Some(searching) = searching {
+        searching.stop();
+    }
+}
+
+/// Search given position deepening iteratively while `tm` allows and until `STOP` of the
+/// evaluator is set, printing `info` of each iteration
+fn search(ban: &Ban, tm: &mut TimeManager) -> BestMove {
+    if let Some(hand) = mate_in_one(ban) {
+        let info = Info {
+            depth: Some(1),
+            score: Some(Score::Mate(Some(1), true)),
+            pv: vec![hand.clone()],
+            ..Info::default()
+        };
+        println!("{}", Response::Info(info));
+        return BestMove::Move { hand, ponder: None };
+    }
+    let start = Instant::now();
+    let mut best = None;
+    let mut last = Duration::default();
+    for depth in 1..=MAX_DEPTH {
+        let elapsed = start.elapsed();
+        if best.is_some() && !tm.can_iterate(elapsed, last * ITERATION_GROWTH) {
+            break;
+        }
+        let result = alpha_beta2(ban, Vec::new(), -50000, 50000, depth, ban.turn);
+        let nodes = evaluator::COUNT.swap(0, Ordering::Relaxed);
+        last = start.elapsed() - elapsed;
+        // the result of a stopped iteration is incomplete
+        if evaluator::STOP.load(Ordering::Relaxed) {
+            break;
+        }
+        // no move is found when the side to move is already checkmated
+        let (hands, value) = match result {
+            Some((hands, value)) if !hands.is_empty() => (hands, value),
+            _ => break,
+        };
+        // scores are from sente, but USI wants the one of the side to move
+        let score = if ban.turn { value } else { -value };
+        tm.report(&hands[0], score);
+        best = Some(hands[0].clone());
+        let info = Info {
+            depth: Some(depth),
+            time: Some(start.elapsed()),
+            nodes: Some(nodes as u64),
+            score: Some(Score::Cp(score)),
+            pv: hands,
+            ..Info::default()
+        };
+        println!("{}", Response::Info(info));
+    }
+    match best {
+        Some(hand) => BestMove::Move { hand, ponder: None },
+        None => {
+            let bans = ban.get_possibility_bans(ban.turn);
+            if bans.is_empty() {
+                BestMove::Resign
+            } else {
+                let ran = thread_rng().gen_range(0..bans.len());
+                BestMove::Move {
+                    hand: bans[ran].1.clone(),
+                    ponder: None,
+                }
             }
-            _ => panic!(),
         }
     }
 }
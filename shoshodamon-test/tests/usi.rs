@@ -0,0 +1,249 @@
+//! Drive the front-end binary with the USI engine controller
+
+use std::{
+    convert::TryInto,
+    thread,
+    time::{Duration, Instant},
+};
+
+use shoshodamon::{
+    record::GameRecord,
+    usi::{BestMove, Checkmate, Command, GoParams, Response, Score},
+    usi_engine::UsiEngine,
+};
+
+fn spawn() -> UsiEngine {
+    UsiEngine::spawn(env!("CARGO_BIN_EXE_shoshodamon-test"), &[]).unwrap()
+}
+
+#[test]
+fn handshake_and_search() {
+    let mut engine = spawn();
+    assert_eq!(engine.name(), "Shoshodamon v0.0.1");
+    assert_eq!(engine.author(), "MysteryJump");
+    assert!(engine
+        .options()
+        .iter()
+        .any(|option| option.name == "BookFile"));
+    assert!(engine
+        .options()
+        .iter()
+        .any(|option| option.name == "USI_Ponder"));
+    assert!(!engine
+        .options()
+        .iter()
+        .any(|option| option.name == "USI_Hash"));
+    // GUIs send USI_Hash even when it is not declared, which is accepted without a reply
+    engine.set_option("USI_Hash", "1024").unwrap();
+    engine.set_option("BookVariety", "10").unwrap();
+    engine.send("isready").unwrap();
+    assert_eq!(
+        engine.receive(Instant::now() + Duration::from_secs(10)),
+        Ok("readyok".to_string())
+    );
+    engine.new_game().unwrap();
+
+    let record = GameRecord::from_usi("sfen 4k4/9/4P4/9/9/9/9/9/4K4 b G 1").unwrap();
+    engine.position(&record).unwrap();
+    let params = GoParams {
+        byoyomi: Some(Duration::from_secs(10)),
+        ..GoParams::default()
+    };
+    let result = engine
+        .go(&params, Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        result.best,
+        BestMove::Move {
+            hand: "G*5b".try_into().unwrap(),
+            ponder: None,
+        }
+    );
+    assert_eq!(result.last_score(), Some(Score::Mate(Some(1), true)));
+    assert_eq!(result.infos[0].pv.len(), 1);
+
+    assert_eq!(
+        engine.go_mate(Some(Duration::from_secs(10))).unwrap(),
+        Checkmate::Mate(vec!["G*5b".try_into().unwrap()])
+    );
+    let record = GameRecord::from_usi("sfen 4k4/9/9/9/9/9/9/9/4K4 b - 1").unwrap();
+    engine.position(&record).unwrap();
+    assert_eq!(engine.go_mate(None).unwrap(), Checkmate::NoMate);
+    engine.game_over(Some(true)).unwrap();
+}
+
+#[test]
+fn search_timeout() {
+    let mut engine = spawn();
+    engine.is_ready().unwrap();
+    engine
+        .position(&GameRecord::from_usi("startpos").unwrap())
+        .unwrap();
+    let params = GoParams {
+        infinite: true,
+        ..GoParams::default()
+    };
+    assert_eq!(
+        engine.go(&params, Duration::from_millis(100)).unwrap(),
+        None
+    );
+    // the bestmove after stop was drained
+    engine.is_ready().unwrap();
+}
+
+#[test]
+fn infinite_and_ponder() {
+    let mut engine = spawn();
+    engine.is_ready().unwrap();
+    let record = GameRecord::from_usi("sfen 4k4/9/4P4/9/9/9/9/9/4K4 b G 1").unwrap();
+    engine.position(&record).unwrap();
+    let short = || Instant::now() + Duration::from_millis(300);
+    let long = || Instant::now() + Duration::from_secs(10);
+
+    // the mate is found at once, but bestmove waits for stop
+    engine
+        .command(&Command::Go(GoParams {
+            infinite: true,
+            ..GoParams::default()
+        }))
+        .unwrap();
+    assert!(engine.wait_bestmove(short()).is_err());
+    engine.stop().unwrap();
+    let result = engine.wait_bestmove(long()).unwrap();
+    assert_eq!(
+        result.best,
+        BestMove::Move {
+            hand: "G*5b".try_into().unwrap(),
+            ponder: None,
+        }
+    );
+
+    // pondering lasts until ponderhit, and the clock applies from then
+    engine
+        .position(&GameRecord::from_usi("startpos moves 7g7f 3c3d").unwrap())
+        .unwrap();
+    engine
+        .command(&Command::Go(GoParams {
+            byoyomi: Some(Duration::from_millis(500)),
+            ponder: true,
+            ..GoParams::default()
+        }))
+        .unwrap();
+    assert!(engine.wait_bestmove(short()).is_err());
+    engine.ponderhit().unwrap();
+    let result = engine
+        .wait_bestmove(Instant::now() + Duration::from_secs(2))
+        .unwrap();
+    assert!(matches!(result.best, BestMove::Move { .. }));
+
+    // stop interrupts a long search
+    engine
+        .command(&Command::Go(GoParams {
+            btime: Duration::from_secs(3600),
+            wtime: Duration::from_secs(3600),
+            ..GoParams::default()
+        }))
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    engine.stop().unwrap();
+    let result = engine
+        .wait_bestmove(Instant::now() + Duration::from_secs(1))
+        .unwrap();
+    assert!(matches!(result.best, BestMove::Move { .. }));
+    engine.is_ready().unwrap();
+}
+
+#[test]
+fn mate_timeout_and_stop() {
+    let mut engine = spawn();
+    engine.is_ready().unwrap();
+    // a long search without a mate of a few plies
+    let record = GameRecord::from_usi("sfen 9/9/4k4/9/9/9/9/9/4K4 b RB 1").unwrap();
+    engine.position(&record).unwrap();
+    assert_eq!(
+        engine.go_mate(Some(Duration::from_millis(300))).unwrap(),
+        Checkmate::Timeout
+    );
+
+    engine.command(&Command::GoMate(None)).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    engine.stop().unwrap();
+    let deadline = Instant::now() + Duration::from_secs(1);
+    assert_eq!(
+        engine.receive_response(deadline).unwrap(),
+        Response::Checkmate(Checkmate::Timeout)
+    );
+    engine.is_ready().unwrap();
+}
+
+#[test]
+fn time_control() {
+    let mut engine = spawn();
+    engine.set_option("ByoyomiMargin", "200").unwrap();
+    engine.is_ready().unwrap();
+    engine
+        .position(&GameRecord::from_usi("startpos moves 7g7f 3c3d").unwrap())
+        .unwrap();
+    let params = GoParams {
+        byoyomi: Some(Duration::from_secs(1)),
+        ..GoParams::default()
+    };
+    let result = engine.go(&params, Duration::from_secs(1)).unwrap();
+    assert!(matches!(result.unwrap().best, BestMove::Move { .. }));
+}
+
+#[test]
+fn checkmated() {
+    let mut engine = spawn();
+    engine.is_ready().unwrap();
+    engine
+        .position(&GameRecord::from_usi("sfen 4k4/4G4/4G4/9/9/9/9/9/4K4 w - 1").unwrap())
+        .unwrap();
+    let params = GoParams {
+        btime: Duration::from_secs(1),
+        wtime: Duration::from_secs(1),
+        ..GoParams::default()
+    };
+    let result = engine.go(&params, Duration::from_secs(10)).unwrap();
+    assert_eq!(result.unwrap().best, BestMove::Resign);
+    // the engine is still alive
+    engine.is_ready().unwrap();
+}
+
+#[test]
+fn spawn_failure() {
+    assert!(UsiEngine::spawn("/nonexistent/engine", &[]).is_err());
+}
+
+#[test]
+fn malformed_commands() {
+    let mut engine = spawn();
+    for line in [
+        "",
+        "   ",
+        "unknown",
+        "position",
+        "position sfen 9/9 b",
+        "go btime x",
+        "setoption name BookVariety value -1",
+        "setoption name NoSuchOption value 1",
+        "setoption name BookFile value /nonexistent/book.db",
+    ]
+    .iter()
+    {
+        engine.send(line).unwrap();
+    }
+    engine.is_ready().unwrap();
+
+    // the invalid position replaces the previous one
+    engine
+        .position(&GameRecord::from_usi("sfen 4k4/9/4P4/9/9/9/9/9/4K4 b G 1").unwrap())
+        .unwrap();
+    engine.send("position startpos moves 7g7f 7g7f").unwrap();
+    let result = engine
+        .go(&GoParams::default(), Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.best, BestMove::Resign);
+}
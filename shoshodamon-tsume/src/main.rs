@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, fmt::Write, fs, process};
+
+use shoshodamon::{
+    ban2::Ban2 as Ban,
+    mate::{solve_mate, MateResult},
+};
+
+const USAGE: &str = "usage: shoshodamon-tsume <file> [--depth <plies>] [--nodes <count>]";
+
+enum Outcome {
+    Solved(Vec<String>),
+    Mismatch(Vec<String>, Vec<String>),
+    NoMate,
+    Unknown,
+    Invalid(String),
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut path = None;
+    let mut max_depth = 15;
+    let mut max_nodes = 1_000_000;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--depth" | "--nodes" if i + 1 < args.len() => {
+                let value = args[i + 1].parse().unwrap_or_else(|_| exit_with_usage());
+                if args[i] == "--depth" {
+                    max_depth = value;
+                } else {
+                    max_nodes = value;
+                }
+                i += 1;
+            }
+            s if path.is_none() && !s.starts_with("--") => path = Some(s.to_string()),
+            _ => exit_with_usage(),
+        }
+        i += 1;
+    }
+    let path = path.unwrap_or_else(|| exit_with_usage());
+    let content = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("cannot read {}: {}", path, e);
+        process::exit(1);
+    });
+
+    let mut outcomes = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let outcome = solve_line(line, max_depth, max_nodes);
+        let (status, moves) = match &outcome {
+            Outcome::Solved(moves) => ("solved", moves.join(" ")),
+            Outcome::Mismatch(moves, expected) => (
+                "mismatch",
+                format!("{} (expected {})", moves.join(" "), expected.join(" ")),
+            ),
+            Outcome::NoMate => ("no mate", String::new()),
+            Outcome::Unknown => ("limit", String::new()),
+            Outcome::Invalid(e) => ("invalid", e.clone()),
+        };
+        let row = format!("{:>5} {:<8} {}", line_no + 1, status, moves);
+        println!("{}", row.trim_end());
+        outcomes.push(outcome);
+    }
+
+    print!("{}", summary(&outcomes));
+}
+
+fn solve_line(line: &str, max_depth: usize, max_nodes: usize) -> Outcome {
+    let fields = line
+        .strip_prefix("sfen ")
+        .unwrap_or(line)
+        .split_ascii_whitespace()
+        .collect::<Vec<_>>();
+    if fields.len() < 4 {
+        return Outcome::Invalid("too few sfen fields".to_string());
+    }
+    let ban = match Ban::from_sfen(&fields[..4].join(" ")) {
+        Ok(ban) => ban,
+        Err(e) => return Outcome::Invalid(e.to_string()),
+    };
+    let expected = fields[4..]
+        .iter()
+        .filter(|s| **s != "moves")
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+
+    match solve_mate(&ban, max_depth, max_nodes) {
+        MateResult::Mate(hands) => {
+            let moves = hands.into_iter().map(String::from).collect::<Vec<_>>();
+            if expected.is_empty() || expected == moves {
+                Outcome::Solved(moves)
+            } else {
+                Outcome::Mismatch(moves, expected)
+            }
+        }
+        MateResult::NoMate => Outcome::NoMate,
+        MateResult::Unknown => Outcome::Unknown,
+    }
+}
+
+/// Table of the counts of outcomes and the lengths of found mates
+fn summary(outcomes: &[Outcome]) -> String {
+    let mut lengths = BTreeMap::new();
+    let (mut solved, mut mismatch, mut no_mate, mut unknown, mut invalid) = (0, 0, 0, 0, 0);
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Solved(moves) | Outcome::Mismatch(moves, _) => {
+                *lengths.entry(moves.len()).or_insert(0) += 1;
+                if let Outcome::Mismatch(..) = outcome {
+                    mismatch += 1;
+                } else {
+                    solved += 1;
+                }
+            }
+            Outcome::NoMate => no_mate += 1,
+            Outcome::Unknown => unknown += 1,
+            Outcome::Invalid(_) => invalid += 1,
+        }
+    }
+
+    let mut table = String::new();
+    let rows = [
+        ("problems", outcomes.len()),
+        ("solved", solved),
+        ("mismatch", mismatch),
+        ("no mate", no_mate),
+        ("limit", unknown),
+        ("invalid", invalid),
+    ];
+    table.push('\n');
+    for (name, count) in rows.iter() {
+        writeln!(table, "{:<10} {:>6}", name, count).unwrap();
+    }
+    if !lengths.is_empty() {
+        table.push('\n');
+        writeln!(table, "{:<10} {:>6}", "length", "count").unwrap();
+        for (length, count) in lengths {
+            writeln!(table, "{:<10} {:>6}", length, count).unwrap();
+        }
+    }
+    table
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+#[test]
+fn solve_lines() {
+    let solved = solve_line("sfen 7gk/9/7+R1/9/9/9/9/9/K8 b G 1", 7, 100_000);
+    assert!(matches!(&solved, Outcome::Solved(moves) if moves == &["2c1c", "2a1b", "G*2b"]));
+    let expected = solve_line(
+        "7gk/9/7+R1/9/9/9/9/9/K8 b G 1 moves 2c1c 2a1b G*2b",
+        7,
+        100_000,
+    );
+    assert!(matches!(expected, Outcome::Solved(_)));
+    let mismatch = solve_line("7gk/9/7+R1/9/9/9/9/9/K8 b G 1 G*2b", 7, 100_000);
+    assert!(
+        matches!(&mismatch, Outcome::Mismatch(moves, expected) if moves.len() == 3 && expected == &["G*2b"])
+    );
+    let no_mate = solve_line("8k/9/9/9/9/9/9/9/K8 b P 1", 5, 100_000);
+    assert!(matches!(no_mate, Outcome::NoMate));
+    let unknown = solve_line("7gk/9/7+R1/9/9/9/9/9/K8 b G 1", 7, 1);
+    assert!(matches!(unknown, Outcome::Unknown));
+    assert!(matches!(
+        solve_line("8k/9/9 b", 5, 100),
+        Outcome::Invalid(_)
+    ));
+    assert!(matches!(
+        solve_line("8k/9/9/9/9/9/9/9/K8 x P 1", 5, 100),
+        Outcome::Invalid(_)
+    ));
+
+    let table = summary(&[solved, mismatch, no_mate, unknown]);
+    assert_eq!(
+        table,
+        "
+problems        4
+solved          1
+mismatch        1
+no mate         1
+limit           1
+invalid         0
+
+length      count
+3               2
+"
+    );
+}
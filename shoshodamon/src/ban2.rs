@@ -1,10 +1,12 @@
-use std::{
-    cmp::Ordering,
-    collections::{hash_map::Entry, HashMap},
-    convert::TryInto,
-};
+use std::{cmp::Ordering, convert::TryInto};
+
+pub mod render;
+pub mod svg;
 
-use crate::{Hand, OnBoardPiece, Piece, PieceBoolPair};
+use crate::{
+    error::{Error, IllegalMoveKind, SfenError, SfenField, Violation},
+    Hand, OnBoardPiece, Piece, PieceBoolPair,
+};
 
 /*
 (0-indexed)
@@ -24,6 +26,35 @@ pub struct Ban2 {
     states: [Option<OnBoardPiece>; 81],
     pub primary_pieces: Vec<Piece>,
     pub secondary_pieces: Vec<Piece>,
+    /// Move count written in sfen
+    pub ply: usize,
+}
+
+/// Pieces except Ou in the order of sfen havings, with the number of each piece in a game
+const HAVING_ORDER: [(Piece, usize); 7] = [
+    (Piece::Hisha, 2),
+    (Piece::Kaku, 2),
+    (Piece::Kin, 4),
+    (Piece::Gin, 4),
+    (Piece::Keima, 4),
+    (Piece::Kyosha, 4),
+    (Piece::Fu, 18),
+];
+
+/// Serialized as sfen
+#[cfg(feature = "serde")]
+impl serde::Serialize for Ban2 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_sfen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Ban2 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let sfen = String::deserialize(deserializer)?;
+        Self::from_sfen(&sfen).map_err(serde::de::Error::custom)
+    }
 }
 
 impl Ban2 {
@@ -32,102 +63,303 @@ impl Ban2 {
     }
 
     /// Create a `Ban` from given sfen
-    pub fn from_sfen(sfen: &str) -> Result<Self, &'static str> {
-        let splited = sfen.trim().split_ascii_whitespace().collect::<Vec<_>>();
-        let (bans, hand, havings) = (
-            splited[0].trim().split('/').collect::<Vec<_>>(),
-            splited[1],
-            splited[2],
+    pub fn from_sfen(sfen: &str) -> Result<Self, Error> {
+        let splited = Self::split_sfen(sfen);
+        let field = |i: usize, field: SfenField| {
+            splited.get(i).copied().ok_or(SfenError {
+                field,
+                position: sfen.chars().count(),
+                message: "missing field",
+            })
+        };
+        let (bans, hand, havings, move_count) = (
+            field(0, SfenField::Board)?,
+            field(1, SfenField::Turn)?,
+            field(2, SfenField::Havings)?,
+            field(3, SfenField::MoveCount)?,
         );
+        if let Some((position, _)) = splited.get(4) {
+            return Err(SfenError {
+                field: SfenField::MoveCount,
+                position: *position,
+                message: "unexpected field after move count",
+            }
+            .into());
+        }
 
-        let turn = match hand {
+        let turn = match hand.1 {
             "b" => true,
             "w" => false,
-            _ => return Err("Cannot parse sfen - near turn"),
+            _ => {
+                return Err(SfenError {
+                    field: SfenField::Turn,
+                    position: hand.0,
+                    message: "turn must be b or w",
+                }
+                .into())
+            }
+        };
+
+        let ply = move_count
+            .1
+            .parse::<usize>()
+            .ok()
+            .filter(|ply| *ply > 0 && move_count.1.chars().all(|c| c.is_ascii_digit()))
+            .ok_or(SfenError {
+                field: SfenField::MoveCount,
+                position: move_count.0,
+                message: "move count must be a positive number",
+            })?;
+
+        let mut ban2 = Ban2 {
+            turn,
+            states: [None; 81],
+            primary_pieces: Vec::with_capacity(38),
+            secondary_pieces: Vec::with_capacity(38),
+            ply,
+        };
+        // the number of each piece and Ou of both turns
+        let mut counts = [0; HAVING_ORDER.len()];
+        let mut ou_counts = [0; 2];
+
+        let board_error = |position: usize, message: &'static str| SfenError {
+            field: SfenField::Board,
+            position: bans.0 + position,
+            message,
         };
+        let ranks = bans.1.split('/').collect::<Vec<_>>();
+        if ranks.len() != 9 {
+            return Err(board_error(0, "board must have 9 ranks").into());
+        }
+        let mut rank_start = 0;
+        for (i, rank) in ranks.iter().enumerate() {
+            let mut current_ind = 0;
+            let mut chars = rank.chars().enumerate();
+            while let Some((j, ch)) = chars.next() {
+                let j = rank_start + j;
+                if ('1'..='9').contains(&ch) {
+                    current_ind += ch as usize - '0' as usize;
+                    if current_ind > 9 {
+                        return Err(board_error(j, "rank has more than 9 squares").into());
+                    }
+                    continue;
+                }
 
-        let mut primary_havings: Vec<Piece> = Vec::with_capacity(38);
-        let mut secondary_havings = Vec::with_capacity(38);
-        let mut havings_chars = havings.chars();
-        if havings != "-" {
-            while let Some(ch) = havings_chars.next() {
-                let (ch, num) = if ch.is_digit(10) {
-                    let num = ch as i32 - '0' as i32;
-                    (
-                        havings_chars
-                            .next()
-                            .ok_or("Cannot parse sfen - near havings")?,
-                        num,
-                    )
+                let is_promoted = ch == '+';
+                let (j, ch) = if is_promoted {
+                    chars
+                        .next()
+                        .map(|(k, c)| (rank_start + k, c))
+                        .ok_or_else(|| board_error(j + 1, "promoted piece is missing"))?
                 } else {
-                    (ch, 1)
+                    (j, ch)
                 };
-                if ch.is_ascii_alphabetic() {
-                    let p = ch
-                        .try_into()
-                        .map_err(|_| "Cannot parse sfen - near havings")?;
-                    if ch.is_uppercase() {
-                        (0..num).for_each(|_| primary_havings.push(p));
-                    } else {
-                        (0..num).for_each(|_| secondary_havings.push(p));
+                let piece: Piece = if ch.is_ascii_alphabetic() {
+                    ch.try_into().map_err(|_| board_error(j, "invalid piece"))?
+                } else {
+                    return Err(board_error(j, "invalid character").into());
+                };
+                if is_promoted && matches!(piece, Piece::Kin | Piece::Ou) {
+                    return Err(board_error(j, "Kin and Ou cannot promote").into());
+                }
+                if current_ind >= 9 {
+                    return Err(board_error(j, "rank has more than 9 squares").into());
+                }
+
+                let piece_turn = ch.is_ascii_uppercase();
+                if piece == Piece::Ou {
+                    let count = &mut ou_counts[piece_turn as usize];
+                    *count += 1;
+                    if *count > 1 {
+                        return Err(board_error(j, "too many Ou").into());
                     }
                 } else {
-                    return Err("Cannot parse sfen - near havings");
+                    let (ind, (_, max)) = Self::find_having_order(piece);
+                    counts[ind] += 1;
+                    if counts[ind] > *max {
+                        return Err(board_error(j, "too many pieces").into());
+                    }
                 }
+
+                ban2.update_position(
+                    9 - current_ind,
+                    i + 1,
+                    Some(OnBoardPiece {
+                        piece,
+                        promoted: is_promoted,
+                        turn: piece_turn,
+                    }),
+                );
+                current_ind += 1;
+            }
+            if current_ind != 9 {
+                return Err(board_error(
+                    rank_start + rank.chars().count(),
+                    "rank must have 9 squares",
+                )
+                .into());
             }
+            rank_start += rank.chars().count() + 1;
         }
 
-        let mut ban2 = Ban2 {
-            turn,
-            states: [None; 81],
-            primary_pieces: primary_havings,
-            secondary_pieces: secondary_havings,
+        let havings_error = |position: usize, message: &'static str| SfenError {
+            field: SfenField::Havings,
+            position: havings.0 + position,
+            message,
         };
-
-        for i in 0..9 {
-            let mut current_ind = 0;
-            let mut chars = bans[i].chars();
-            while let Some(ch) = chars.next() {
-                if ch.is_digit(10) {
-                    let num = ch as usize - '0' as usize;
-                    current_ind += num;
-                } else if ch.is_ascii_alphabetic() || ch == '+' {
-                    let is_promoted = ch == '+';
-                    let piece = if is_promoted {
-                        let c = chars.next().ok_or("Cannot parse board - near board1")?;
-                        (
-                            c.try_into()
-                                .map_err(|_| "Cannot parse board - near board2")?,
-                            c.is_uppercase(),
-                        )
-                    } else {
-                        (
-                            ch.try_into()
-                                .map_err(|_| "Cannot parse board - near board2")?,
-                            ch.is_uppercase(),
-                        )
-                    };
-
-                    ban2.update_position(
-                        9 - current_ind,
-                        i + 1,
-                        Some(OnBoardPiece {
-                            piece: piece.0,
-                            promoted: is_promoted,
-                            turn: piece.1,
-                        }),
-                    );
-
-                    current_ind += 1;
+        if havings.1 != "-" {
+            let mut havings_chars = havings.1.chars().enumerate().peekable();
+            while let Some((i, ch)) = havings_chars.next() {
+                let mut num = 1;
+                let mut ch = (i, ch);
+                if ch.1.is_ascii_digit() {
+                    num = ch.1 as usize - '0' as usize;
+                    if let Some((_, c)) = havings_chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                        num = num * 10 + (*c as usize - '0' as usize);
+                        havings_chars.next();
+                    }
+                    if num == 0 {
+                        return Err(havings_error(i, "count must be positive").into());
+                    }
+                    ch = havings_chars.next().ok_or_else(|| {
+                        havings_error(havings.1.chars().count(), "piece is missing")
+                    })?;
+                }
+                let piece: Piece = if ch.1.is_ascii_alphabetic() {
+                    ch.1.try_into()
+                        .map_err(|_| havings_error(ch.0, "invalid piece"))?
                 } else {
-                    return Err("Cannot parse board - near board3");
+                    return Err(havings_error(ch.0, "invalid character").into());
+                };
+                if piece == Piece::Ou {
+                    return Err(havings_error(ch.0, "Ou cannot be had").into());
                 }
+                let (ind, (_, max)) = Self::find_having_order(piece);
+                counts[ind] += num;
+                if counts[ind] > *max {
+                    return Err(havings_error(i, "too many pieces").into());
+                }
+                let pieces = if ch.1.is_ascii_uppercase() {
+                    &mut ban2.primary_pieces
+                } else {
+                    &mut ban2.secondary_pieces
+                };
+                (0..num).for_each(|_| pieces.push(piece));
             }
         }
+
         Ok(ban2)
     }
 
-    /// Create a sfen-formatted string from current `Ban` status
+    /// Create a `Ban` from given sfen and reject it unless `validate` passes
+    pub fn from_sfen_validated(sfen: &str) -> Result<Self, Error> {
+        let ban = Self::from_sfen(sfen)?;
+        ban.validate().map_err(Error::InvalidPosition)?;
+        Ok(ban)
+    }
+
+    /// Check the position can appear in a game, returning every violation
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+
+        for turn in [true, false] {
+            let ou_count = self
+                .states
+                .iter()
+                .flatten()
+                .filter(|p| p.piece == Piece::Ou && p.turn == turn)
+                .count();
+            match ou_count {
+                0 => violations.push(Violation::NoOu { turn }),
+                1 => {}
+                count => violations.push(Violation::TooManyOu { turn, count }),
+            }
+
+            for x in 1..=9 {
+                let fu_count = (1..=9)
+                    .filter_map(|y| *self.get_position(x, y))
+                    .filter(|p| p.piece == Piece::Fu && !p.promoted && p.turn == turn)
+                    .count();
+                if fu_count > 1 {
+                    violations.push(Violation::Nifu { turn, x });
+                }
+            }
+        }
+
+        for x in 1..=9 {
+            for y in 1..=9 {
+                if let Some(p) = self.get_position(x, y) {
+                    if !p.promoted && !Self::can_stay(p.piece, p.turn, y) {
+                        violations.push(Violation::NoNextMove { x, y });
+                    }
+                }
+            }
+        }
+
+        for (piece, max) in HAVING_ORDER.iter() {
+            let count = self
+                .states
+                .iter()
+                .flatten()
+                .filter(|p| p.piece == *piece)
+                .count()
+                + self
+                    .primary_pieces
+                    .iter()
+                    .chain(self.secondary_pieces.iter())
+                    .filter(|p| *p == piece)
+                    .count();
+            if count > *max {
+                violations.push(Violation::TooManyPieces {
+                    piece: *piece,
+                    count,
+                });
+            }
+        }
+
+        if self.is_check(!self.turn) {
+            violations.push(Violation::OpponentChecked);
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Get the index and entry of given piece in `HAVING_ORDER`
+    fn find_having_order(piece: Piece) -> (usize, &'static (Piece, usize)) {
+        HAVING_ORDER
+            .iter()
+            .enumerate()
+            .find(|(_, (p, _))| *p == piece)
+            .expect("Ou is not in HAVING_ORDER")
+    }
+
+    /// Split sfen into fields with their character positions
+    fn split_sfen(sfen: &str) -> Vec<(usize, &str)> {
+        let mut fields = Vec::new();
+        let mut start = None;
+        for (i, (byte, ch)) in sfen.char_indices().enumerate() {
+            match (ch.is_ascii_whitespace(), start) {
+                (false, None) => start = Some((i, byte)),
+                (true, Some((pos, from))) => {
+                    fields.push((pos, &sfen[from..byte]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some((pos, from)) = start {
+            fields.push((pos, &sfen[from..]));
+        }
+        fields
+    }
+
+    /// Create a sfen-formatted string from current `Ban` status.
+    /// Havings are written in the order of R, B, G, S, N, L, P, sente first, so that equal
+    /// positions always give the same sfen.
     pub fn to_sfen(&self) -> String {
         let mut sfen = String::new();
         for y in 1..=9 {
@@ -156,37 +388,26 @@ impl Ban2 {
         sfen.push(' ');
 
         if !self.primary_pieces.is_empty() || !self.secondary_pieces.is_empty() {
-            let mut map = HashMap::new();
-            for piece in self
-                .primary_pieces
-                .iter()
-                .map(|p| PieceBoolPair(*p, true))
-                .chain(
-                    self.secondary_pieces
-                        .iter()
-                        .map(|p| PieceBoolPair(*p, false)),
-                )
-            {
-                let ch: char = piece.into();
-                if let Entry::Vacant(e) = map.entry(ch) {
-                    e.insert(1);
-                } else {
-                    let c = map.get_mut(&ch).unwrap();
-                    *c += 1;
-                }
-            }
-            for (p, c) in map {
-                if c != 1 {
-                    sfen.push_str(&c.to_string());
+            for (turn, pieces) in [
+                (true, &self.primary_pieces),
+                (false, &self.secondary_pieces),
+            ] {
+                for (piece, _) in HAVING_ORDER.iter() {
+                    let count = pieces.iter().filter(|p| *p == piece).count();
+                    if count > 1 {
+                        sfen.push_str(&count.to_string());
+                    }
+                    if count > 0 {
+                        sfen.push(PieceBoolPair(*piece, turn).into());
+                    }
                 }
-                sfen.push(p);
             }
         } else {
             sfen.push('-');
         }
         sfen.push(' ');
 
-        sfen.push('1');
+        sfen.push_str(&self.ply.to_string());
         sfen
     }
 
@@ -228,24 +449,38 @@ impl Ban2 {
         after_x: usize,
         after_y: usize,
         with_promote: bool,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), Error> {
+        if ![before_x, before_y, after_x, after_y]
+            .iter()
+            .all(|v| (1..=9).contains(v))
+        {
+            return Err(IllegalMoveKind::OutOfBoard.into());
+        }
         let piece = if let Some(piece) = *self.get_position(before_x, before_y) {
             if piece.turn != self.turn {
-                Err("Specified position is not your piece")
+                Err(IllegalMoveKind::NotYourPiece)
             } else {
                 Ok(piece)
             }
         } else {
-            Err("Does not exist any piece at given position")
+            Err(IllegalMoveKind::NoPiece)
         }?;
 
         let dx = after_x as isize - before_x as isize;
         let dy = after_y as isize - before_y as isize;
 
-        if with_promote && (self.turn && after_y > 3 || !self.turn && after_y < 7) {
-            return Err("Cannot promote your piece at specified moved position");
+        let in_zone = |y: usize| self.turn && y <= 3 || !self.turn && y >= 7;
+        if with_promote && !in_zone(before_y) && !in_zone(after_y) {
+            return Err(IllegalMoveKind::OutOfPromotionZone.into());
         } else if with_promote && piece.promoted {
-            return Err("Cannot promote your piece which already promoted");
+            return Err(IllegalMoveKind::AlreadyPromoted.into());
+        } else if with_promote && matches!(piece.piece, Piece::Kin | Piece::Ou) {
+            return Err(IllegalMoveKind::Unpromotable.into());
+        } else if !with_promote
+            && !piece.promoted
+            && !Self::can_stay(piece.piece, self.turn, after_y)
+        {
+            return Err(IllegalMoveKind::NoNextMove.into());
         }
 
         let movements = piece
@@ -256,13 +491,13 @@ impl Ban2 {
             || match piece.piece {
                 Piece::Kaku => self.check_kaku_movement(before_x, before_y, dx, dy),
                 Piece::Hisha => self.check_hisha_movement(before_x, before_y, dx, dy),
-                Piece::Kyosha => self.check_kyosha_movement(before_x, before_y, dy, &piece),
+                Piece::Kyosha => self.check_kyosha_movement(before_x, before_y, dx, dy, &piece),
                 _ => false,
             }
         {
             if let Some(last_piece) = *self.get_position(after_x, after_y) {
                 if last_piece.turn == self.turn {
-                    return Err("Cannot move piece to your piece");
+                    return Err(IllegalMoveKind::OccupiedByYourPiece.into());
                 }
 
                 if piece.turn {
@@ -282,9 +517,31 @@ impl Ban2 {
             );
             self.update_position(before_x, before_y, None);
             self.turn = !self.turn;
+            self.ply += 1;
             Ok(())
         } else {
-            Err("Cannot move piece to specified position")
+            Err(IllegalMoveKind::CannotReach.into())
+        }
+    }
+
+    /// Move or put a piece by given `Hand`
+    pub fn apply_hand(&mut self, hand: &Hand) -> Result<(), Error> {
+        match *hand {
+            Hand::Movement {
+                x,
+                y,
+                dx,
+                dy,
+                with_promote,
+            } => {
+                let ax = x as isize + dx;
+                let ay = y as isize + dy;
+                if ax < 1 || ay < 1 {
+                    return Err(IllegalMoveKind::OutOfBoard.into());
+                }
+                self.move_piece(x, y, ax as usize, ay as usize, with_promote)
+            }
+            Hand::Putting { piece, x, y } => self.put_piece(piece, x, y),
         }
     }
 
@@ -295,7 +552,7 @@ impl Ban2 {
                 oy @ (Ordering::Less | Ordering::Greater),
             ) => {
                 let xdiff = if ox == Ordering::Less { -1 } else { 1 };
-                let ydiff = if oy == Ordering::Greater { -1 } else { 1 };
+                let ydiff = if oy == Ordering::Less { -1 } else { 1 };
                 let mut i = xdiff;
                 let mut j = ydiff;
                 loop {
@@ -369,10 +626,11 @@ impl Ban2 {
         &self,
         x: usize,
         y: usize,
+        dx: isize,
         dy: isize,
         on_board_piece: &OnBoardPiece,
     ) -> bool {
-        if on_board_piece.promoted {
+        if !on_board_piece.promoted && dx == 0 {
             let diff = if on_board_piece.turn { -1 } else { 1 };
             let mut i = diff;
             loop {
@@ -394,10 +652,22 @@ impl Ban2 {
         }
     }
 
+    /// Whether an unpromoted piece can stay at given rank (Fu, Kyosha and Keima need a next move)
+    fn can_stay(piece: Piece, turn: bool, y: usize) -> bool {
+        match piece {
+            Piece::Fu | Piece::Kyosha => turn && y > 1 || !turn && y < 9,
+            Piece::Keima => turn && y > 2 || !turn && y < 8,
+            _ => true,
+        }
+    }
+
     /// Put a piece with error check. position needs 1-indexed
-    pub fn put_piece(&mut self, piece: Piece, x: usize, y: usize) -> Result<(), &'static str> {
+    pub fn put_piece(&mut self, piece: Piece, x: usize, y: usize) -> Result<(), Error> {
+        if !(1..=9).contains(&x) || !(1..=9).contains(&y) {
+            return Err(IllegalMoveKind::OutOfBoard.into());
+        }
         if self.get_position(x, y).is_some() {
-            return Err("Cannot put a piece on a piece which already exists at specified position");
+            return Err(IllegalMoveKind::Occupied.into());
         }
 
         let pieces = if self.turn {
@@ -409,19 +679,17 @@ impl Ban2 {
         let pos = pieces
             .iter()
             .position(|x| x == &piece)
-            .ok_or("Cannot find the piece in your havings.")?;
+            .ok_or(IllegalMoveKind::OutOfHand)?;
 
         match piece {
             p @ (Piece::Fu | Piece::Kyosha) => {
                 if self.turn && y == 1 || !self.turn && y == 9 {
-                    Err("Cannot put a piece at specified place")
+                    Err(IllegalMoveKind::NoNextMove)
                 } else if p == Piece::Fu {
-                    // TODO: uchifuzume
-
                     for i in 1..=9 {
                         if let Some(s) = self.get_position(x, i) {
                             if !s.promoted && s.piece == Piece::Fu && self.turn == s.turn {
-                                return Err("This movements cause nifu");
+                                return Err(IllegalMoveKind::Nifu.into());
                             }
                         }
                     }
@@ -433,12 +701,12 @@ impl Ban2 {
             Piece::Kaku | Piece::Hisha | Piece::Kin | Piece::Gin => Ok(()),
             Piece::Keima => {
                 if self.turn && y < 3 || !self.turn && y > 7 {
-                    Err("Cannot put Keima at specified place")
+                    Err(IllegalMoveKind::NoNextMove)
                 } else {
                     Ok(())
                 }
             }
-            Piece::Ou => panic!(),
+            Piece::Ou => Err(IllegalMoveKind::PutOu),
         }?;
 
         if self.turn {
@@ -457,23 +725,29 @@ impl Ban2 {
         );
 
         self.turn = !self.turn;
+
+        // uchifuzume: checkmate by putting Fu is not allowed
+        if piece == Piece::Fu && self.is_check(self.turn) && self.get_evasion_bans().is_empty() {
+            self.turn = !self.turn;
+            self.update_position(x, y, None);
+            if self.turn {
+                self.primary_pieces.insert(pos, piece);
+            } else {
+                self.secondary_pieces.insert(pos, piece);
+            }
+            return Err(IllegalMoveKind::Uchifuzume.into());
+        }
+        self.ply += 1;
         Ok(())
     }
 
     /// Check given turn checked.
+    /// Always `false` when given turn has no Ou, e.g. the attacker of tsume shogi.
     pub fn is_check(&self, turn: bool) -> bool {
-        let mut ou = None;
-        for x in 1..=9 {
-            for y in 1..=9 {
-                if let Some(piece) = self.get_position(x, y) {
-                    if piece.piece == Piece::Ou && piece.turn == turn {
-                        ou = Some(((x, y), piece));
-                    }
-                }
-            }
-        }
-
-        let ((x, y), _) = ou.unwrap();
+        let (x, y) = match self.find_ou(turn) {
+            Some(pos) => pos,
+            None => return false,
+        };
         let possibilities_near = [
             (1, 0),
             (1, 1),
@@ -511,7 +785,8 @@ impl Ban2 {
                 let yy = if pn { i } else { -i } + y as isize;
                 if (1..=9).contains(&yy) {
                     if let Some(s) = self.get_position(x, yy as usize) {
-                        if ((turn ^ pn) && s.piece == Piece::Kyosha || s.piece == Piece::Hisha)
+                        if ((turn ^ pn) && s.piece == Piece::Kyosha && !s.promoted
+                            || s.piece == Piece::Hisha)
                             && s.turn != turn
                         {
                             return true;
@@ -615,7 +890,7 @@ impl Ban2 {
                                     for i in 2..=8 {
                                         let diff = if x_pn { i } else { -i };
                                         let xx = x as isize + diff;
-                                        if (2..=9).contains(&xx) {
+                                        if (1..=9).contains(&xx) {
                                             for with_promote in [true, false] {
                                                 if s.promoted && with_promote {
                                                     continue;
@@ -651,7 +926,7 @@ impl Ban2 {
                                 for i in 2..=8 {
                                     let diff = if y_pn { i } else { -i };
                                     let yy = y as isize + diff;
-                                    if (2..=9).contains(&yy) {
+                                    if (1..=9).contains(&yy) {
                                         for with_promote in [true, false] {
                                             if s.promoted && with_promote {
                                                 continue;
@@ -688,7 +963,7 @@ impl Ban2 {
                                     let xx = x as isize + x_diff;
                                     let yy = y as isize + y_diff;
 
-                                    if (2..=9).contains(&xx) && (2..=9).contains(&yy) {
+                                    if (1..=9).contains(&xx) && (1..=9).contains(&yy) {
                                         for with_promote in [true, false] {
                                             if s.promoted && with_promote {
                                                 continue;
@@ -723,22 +998,67 @@ impl Ban2 {
                         }
                     }
                 } else {
-                    let pieces = if turn {
-                        &self.primary_pieces
-                    } else {
-                        &self.secondary_pieces
-                    };
-                    for piece in pieces {
+                    for piece in self.get_having_kinds(turn) {
+                        let mut ban = (*self).clone();
+                        if ban.put_piece(piece, x, y).is_ok() && !ban.is_check(turn) {
+                            bans.push((ban, Hand::Putting { piece, x, y }));
+                        }
+                    }
+                }
+            }
+        }
+        bans
+    }
+
+    /// Get all possibilities of the side to move which check the opponent's Ou.
+    /// Same as filtering `get_possibility_bans` with `is_check(!turn)`, without trying every move.
+    pub fn get_check_bans(&self) -> Vec<(Ban2, Hand)> {
+        let turn = self.turn;
+        let mut bans = Vec::new();
+        let (ox, oy) = match self.find_ou(!turn) {
+            Some(pos) => pos,
+            None => return bans,
+        };
+
+        for x in 1..=9 {
+            for y in 1..=9 {
+                let s = match self.get_position(x, y) {
+                    Some(s) if s.turn == turn => *s,
+                    _ => continue,
+                };
+                let is_discoverer = self.is_discoverer(x, y, ox, oy, turn);
+                for (ax, ay) in self.get_destinations(x, y, &s) {
+                    for with_promote in [true, false] {
+                        let moved = OnBoardPiece {
+                            promoted: s.promoted || with_promote,
+                            ..s
+                        };
+                        if !is_discoverer && !self.can_attack(ax, ay, &moved, ox, oy, Some((x, y)))
+                        {
+                            continue;
+                        }
+                        self.push_if_legal(&mut bans, x, y, ax, ay, with_promote, |b| {
+                            b.is_check(!turn)
+                        });
+                    }
+                }
+            }
+        }
+
+        for piece in self.get_having_kinds(turn) {
+            let put = OnBoardPiece {
+                piece,
+                promoted: false,
+                turn,
+            };
+            for x in 1..=9 {
+                for y in 1..=9 {
+                    if self.get_position(x, y).is_none()
+                        && self.can_attack(x, y, &put, ox, oy, None)
+                    {
                         let mut ban = (*self).clone();
-                        if ban.put_piece(*piece, x, y).is_ok() && !ban.is_check(turn) {
-                            bans.push((
-                                ban,
-                                Hand::Putting {
-                                    piece: *piece,
-                                    x,
-                                    y,
-                                },
-                            ));
+                        if ban.put_piece(piece, x, y).is_ok() && !ban.is_check(turn) {
+                            bans.push((ban, Hand::Putting { piece, x, y }));
                         }
                     }
                 }
@@ -746,8 +1066,294 @@ impl Ban2 {
         }
         bans
     }
+
+    /// Get all possibilities of the side to move which escape from check.
+    /// Returns empty `Vec` when the side to move is not checked.
+    pub fn get_evasion_bans(&self) -> Vec<(Ban2, Hand)> {
+        let turn = self.turn;
+        let mut bans = Vec::new();
+        let (ox, oy) = match self.find_ou(turn) {
+            Some(pos) => pos,
+            None => return bans,
+        };
+        let checkers = self.get_attackers(ox, oy, !turn);
+        if checkers.is_empty() {
+            return bans;
+        }
+
+        let ou = self.get_position(ox, oy).unwrap();
+        for (ax, ay) in self.get_destinations(ox, oy, &ou) {
+            self.push_if_legal(&mut bans, ox, oy, ax, ay, false, |_| true);
+        }
+        if checkers.len() > 1 {
+            return bans;
+        }
+
+        // capture the checker or interpose between the checker and the Ou
+        let (cx, cy) = checkers[0];
+        let between = Self::get_between(cx, cy, ox, oy);
+        for x in 1..=9 {
+            for y in 1..=9 {
+                let s = match self.get_position(x, y) {
+                    Some(s) if s.turn == turn && s.piece != Piece::Ou => *s,
+                    _ => continue,
+                };
+                for (ax, ay) in self.get_destinations(x, y, &s) {
+                    if (ax, ay) != (cx, cy) && !between.contains(&(ax, ay)) {
+                        continue;
+                    }
+                    for with_promote in [true, false] {
+                        self.push_if_legal(&mut bans, x, y, ax, ay, with_promote, |_| true);
+                    }
+                }
+            }
+        }
+        for piece in self.get_having_kinds(turn) {
+            for (x, y) in &between {
+                let mut ban = (*self).clone();
+                if ban.put_piece(piece, *x, *y).is_ok() && !ban.is_check(turn) {
+                    bans.push((
+                        ban,
+                        Hand::Putting {
+                            piece,
+                            x: *x,
+                            y: *y,
+                        },
+                    ));
+                }
+            }
+        }
+        bans
+    }
+
+    /// Get the position of the Ou of given turn.
+    pub fn find_ou(&self, turn: bool) -> Option<(usize, usize)> {
+        for x in 1..=9 {
+            for y in 1..=9 {
+                if let Some(piece) = self.get_position(x, y) {
+                    if piece.piece == Piece::Ou && piece.turn == turn {
+                        return Some((x, y));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Get all positions of the pieces of given turn which attack (x, y)
+    pub fn get_attackers(&self, x: usize, y: usize, turn: bool) -> Vec<(usize, usize)> {
+        let mut attackers = Vec::new();
+        for px in 1..=9 {
+            for py in 1..=9 {
+                if let Some(s) = self.get_position(px, py) {
+                    if s.turn == turn && self.can_attack(px, py, s, x, y, None) {
+                        attackers.push((px, py));
+                    }
+                }
+            }
+        }
+        attackers
+    }
+
+    /// Get kinds of pieces which given turn has, without duplicates
+    fn get_having_kinds(&self, turn: bool) -> Vec<Piece> {
+        let pieces = if turn {
+            &self.primary_pieces
+        } else {
+            &self.secondary_pieces
+        };
+        let mut kinds = Vec::with_capacity(7);
+        for piece in pieces {
+            if !kinds.contains(piece) {
+                kinds.push(*piece);
+            }
+        }
+        kinds
+    }
+
+    /// Try to move piece on a cloned `Ban2` and push it when the move is legal and `pred` holds
+    #[allow(clippy::too_many_arguments)]
+    fn push_if_legal<F: Fn(&Ban2) -> bool>(
+        &self,
+        bans: &mut Vec<(Ban2, Hand)>,
+        x: usize,
+        y: usize,
+        ax: usize,
+        ay: usize,
+        with_promote: bool,
+        pred: F,
+    ) {
+        let mut ban = (*self).clone();
+        if ban.move_piece(x, y, ax, ay, with_promote).is_ok()
+            && !ban.is_check(self.turn)
+            && pred(&ban)
+        {
+            bans.push((
+                ban,
+                Hand::Movement {
+                    x,
+                    y,
+                    dx: ax as isize - x as isize,
+                    dy: ay as isize - y as isize,
+                    with_promote,
+                },
+            ));
+        }
+    }
+
+    /// Get all positions where the piece at (x, y) can move, ignoring check and promotion
+    fn get_destinations(&self, x: usize, y: usize, piece: &OnBoardPiece) -> Vec<(usize, usize)> {
+        let mut destinations = Vec::new();
+        let mut push = |xx: isize, yy: isize| -> bool {
+            if !(1..=9).contains(&xx) || !(1..=9).contains(&yy) {
+                return false;
+            }
+            match self.get_position(xx as usize, yy as usize) {
+                Some(s) if s.turn == piece.turn => false,
+                Some(_) => {
+                    destinations.push((xx as usize, yy as usize));
+                    false
+                }
+                None => {
+                    destinations.push((xx as usize, yy as usize));
+                    true
+                }
+            }
+        };
+
+        for (dx, dy) in piece
+            .piece
+            .get_near_piece_movement(piece.turn, piece.promoted)
+        {
+            if *dx != 0 || *dy != 0 {
+                push(x as isize + dx, y as isize + dy);
+            }
+        }
+        for (dx, dy) in Self::get_sliding_directions(piece) {
+            // the first step is already covered by near movement
+            let mut i = 2;
+            let mut blocked = !matches!(
+                self.get_position_checked(x as isize + dx, y as isize + dy),
+                Some(None)
+            );
+            while !blocked {
+                blocked = !push(x as isize + dx * i, y as isize + dy * i);
+                i += 1;
+            }
+        }
+        destinations
+    }
+
+    /// Get a piece like `get_position`, returning `None` when given position is out of board
+    fn get_position_checked(&self, x: isize, y: isize) -> Option<&Option<OnBoardPiece>> {
+        if (1..=9).contains(&x) && (1..=9).contains(&y) {
+            Some(self.get_position(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    fn get_sliding_directions(piece: &OnBoardPiece) -> &'static [(isize, isize)] {
+        match piece.piece {
+            Piece::Kaku => &[(1, 1), (1, -1), (-1, 1), (-1, -1)],
+            Piece::Hisha => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            Piece::Kyosha if !piece.promoted && piece.turn => &[(0, -1)],
+            Piece::Kyosha if !piece.promoted => &[(0, 1)],
+            _ => &[],
+        }
+    }
+
+    /// Check the piece placed at (x, y) attacks (ax, ay). `ignore` is treated as an empty place
+    fn can_attack(
+        &self,
+        x: usize,
+        y: usize,
+        piece: &OnBoardPiece,
+        ax: usize,
+        ay: usize,
+        ignore: Option<(usize, usize)>,
+    ) -> bool {
+        let dx = ax as isize - x as isize;
+        let dy = ay as isize - y as isize;
+        if dx == 0 && dy == 0 {
+            return false;
+        }
+        if piece
+            .piece
+            .get_near_piece_movement(piece.turn, piece.promoted)
+            .contains(&(dx, dy))
+        {
+            return true;
+        }
+        let direction = (dx.signum(), dy.signum());
+        if !(dx == 0 || dy == 0 || dx.abs() == dy.abs())
+            || !Self::get_sliding_directions(piece).contains(&direction)
+        {
+            return false;
+        }
+        Self::get_between(x, y, ax, ay)
+            .iter()
+            .all(|(bx, by)| Some((*bx, *by)) == ignore || self.get_position(*bx, *by).is_none())
+    }
+
+    /// Check the piece at (x, y) opens a sliding attack to (ox, oy) by moving
+    fn is_discoverer(&self, x: usize, y: usize, ox: usize, oy: usize, turn: bool) -> bool {
+        let dx = x as isize - ox as isize;
+        let dy = y as isize - oy as isize;
+        if !(dx == 0 || dy == 0 || dx.abs() == dy.abs()) {
+            return false;
+        }
+        let (sx, sy) = (dx.signum(), dy.signum());
+        let mut i = 1;
+        while let Some(s) = self.get_position_checked(x as isize + sx * i, y as isize + sy * i) {
+            if let Some(s) = s {
+                let px = (x as isize + sx * i) as usize;
+                let py = (y as isize + sy * i) as usize;
+                return s.turn == turn && self.can_attack(px, py, s, ox, oy, Some((x, y)));
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// Get positions strictly between two positions on the same line. Empty when not on a line
+    fn get_between(x: usize, y: usize, ax: usize, ay: usize) -> Vec<(usize, usize)> {
+        let dx = ax as isize - x as isize;
+        let dy = ay as isize - y as isize;
+        if !(dx == 0 || dy == 0 || dx.abs() == dy.abs()) {
+            return Vec::new();
+        }
+        let (sx, sy) = (dx.signum(), dy.signum());
+        (1..dx.abs().max(dy.abs()))
+            .map(|i| {
+                (
+                    (x as isize + sx * i) as usize,
+                    (y as isize + sy * i) as usize,
+                )
+            })
+            .collect()
+    }
 }
 
+impl PartialEq for Ban2 {
+    fn eq(&self, other: &Self) -> bool {
+        let same_havings = |a: &Vec<Piece>, b: &Vec<Piece>| {
+            a.len() == b.len()
+                && HAVING_ORDER.iter().all(|(piece, _)| {
+                    a.iter().filter(|p| *p == piece).count()
+                        == b.iter().filter(|p| *p == piece).count()
+                })
+        };
+        self.turn == other.turn
+            && self.ply == other.ply
+            && self.states == other.states
+            && same_havings(&self.primary_pieces, &other.primary_pieces)
+            && same_havings(&self.secondary_pieces, &other.secondary_pieces)
+    }
+}
+
+impl Eq for Ban2 {}
+
 impl Default for Ban2 {
     fn default() -> Self {
         Self::new()
@@ -762,3 +1368,267 @@ fn feature() {
     println!("{:?}", ban2.get_position(5, 1));
     println!("{}", ban2.to_sfen());
 }
+
+#[test]
+fn check_and_evasion_bans() {
+    fn to_sorted(bans: Vec<(Ban2, Hand)>) -> Vec<String> {
+        let mut hands = bans
+            .into_iter()
+            .map(|(_, h)| String::from(h))
+            .collect::<Vec<_>>();
+        hands.sort();
+        hands
+    }
+
+    let sfens = [
+        super::START_POS,
+        "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+        "l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w GR5pnsg 1",
+        "4k4/9/4P4/9/9/9/9/9/4K4 b GSNLRB 1",
+        "8k/7B1/9/9/9/9/9/1r7/K8 w G 1",
+        "4k4/4l4/9/9/4R4/9/9/4L4/4K4 b P 1",
+        "7lk/9/8S/9/9/9/9/7L1/K8 b G 1",
+    ];
+    for sfen in sfens {
+        let mut ban = Ban2::from_sfen(sfen).unwrap();
+        for ply in 0..80 {
+            let all = ban.get_possibility_bans(ban.turn);
+            if all.is_empty() {
+                break;
+            }
+            let turn = ban.turn;
+            let checks = all
+                .iter()
+                .filter(|(b, _)| b.is_check(!turn))
+                .cloned()
+                .collect::<Vec<_>>();
+            assert_eq!(
+                to_sorted(ban.get_check_bans()),
+                to_sorted(checks),
+                "{}",
+                ban.to_sfen()
+            );
+            let evasions = if ban.is_check(turn) {
+                all.clone()
+            } else {
+                Vec::new()
+            };
+            assert_eq!(
+                to_sorted(ban.get_evasion_bans()),
+                to_sorted(evasions),
+                "{}",
+                ban.to_sfen()
+            );
+            ban = all[(ply * 7 + 3) % all.len()].0.clone();
+        }
+    }
+}
+
+#[test]
+fn possibility_bans_perft() {
+    fn perft(ban: &Ban2, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        ban.get_possibility_bans(ban.turn)
+            .iter()
+            .map(|(b, _)| perft(b, depth - 1))
+            .sum()
+    }
+
+    let ban = Ban2::new();
+    assert_eq!(perft(&ban, 1), 30);
+    assert_eq!(perft(&ban, 2), 900);
+    assert_eq!(perft(&ban, 3), 25470);
+}
+
+#[test]
+fn errors() {
+    let e = Ban2::from_sfen("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL x - 1");
+    assert_eq!(
+        e.unwrap_err(),
+        Error::Sfen(SfenError {
+            field: SfenField::Turn,
+            position: 58,
+            message: "turn must be b or w",
+        })
+    );
+    let e = Ban2::from_sfen("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b 2X 1");
+    assert!(matches!(
+        e,
+        Err(Error::Sfen(SfenError {
+            field: SfenField::Havings,
+            position: 61,
+            ..
+        }))
+    ));
+
+    let mut ban = Ban2::new();
+    assert_eq!(
+        ban.move_piece(7, 3, 7, 4, false),
+        Err(IllegalMoveKind::NotYourPiece.into())
+    );
+    assert_eq!(
+        ban.move_piece(5, 9, 4, 8, true),
+        Err(IllegalMoveKind::OutOfPromotionZone.into())
+    );
+    assert_eq!(
+        ban.put_piece(Piece::Fu, 5, 5),
+        Err(IllegalMoveKind::OutOfHand.into())
+    );
+}
+
+#[test]
+fn malformed_sfen() {
+    let cases = [
+        ("", SfenField::Board),
+        ("9/9/9/9/9/9/9/9/9 b -", SfenField::MoveCount),
+        ("9/9/9/9/9/9/9/9/9 b - 1 moves", SfenField::MoveCount),
+        ("9/9/9/9/9/9/9/9/9 b - 0", SfenField::MoveCount),
+        ("9/9/9/9/9/9/9/9/9 b - x", SfenField::MoveCount),
+        ("9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("9/9/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("8/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("55/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("9P/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("09/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("+G8/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("K3K4/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("RRR6/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("X8/9/9/9/9/9/9/9/9 b - 1", SfenField::Board),
+        ("9/9/9/9/9/9/9/9/9 B - 1", SfenField::Turn),
+        ("9/9/9/9/9/9/9/9/9 b K 1", SfenField::Havings),
+        ("9/9/9/9/9/9/9/9/9 b 19P 1", SfenField::Havings),
+        ("9/9/9/9/9/9/9/9/9 b 0P 1", SfenField::Havings),
+        ("9/9/9/9/9/9/9/9/9 b 3 1", SfenField::Havings),
+        ("9/9/9/9/9/9/9/9/9 b R2r 1", SfenField::Havings),
+    ];
+    for (sfen, field) in cases {
+        match Ban2::from_sfen(sfen) {
+            Err(Error::Sfen(e)) => assert_eq!(e.field, field, "{}", sfen),
+            r => panic!("{} {:?}", sfen, r.map(|b| b.to_sfen())),
+        }
+    }
+
+    let ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/4K4 w 10P8p2r 42").unwrap();
+    assert_eq!(ban.primary_pieces.len(), 10);
+    assert_eq!(ban.secondary_pieces.len(), 10);
+    assert_eq!(ban.ply, 42);
+}
+
+#[test]
+fn sfen_round_trip() {
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(30);
+    for _ in 0..500 {
+        let mut sfen = String::new();
+        let mut squares = (0..81).collect::<Vec<_>>();
+        squares.shuffle(&mut rng);
+        let mut board = vec![String::new(); 81];
+        let mut havings = String::new();
+        let mut squares = squares.into_iter();
+        for piece in ["K", "k"] {
+            board[squares.next().unwrap()] = piece.to_string();
+        }
+        for (piece, max) in HAVING_ORDER.iter() {
+            for _ in 0..*max {
+                let ch: char = PieceBoolPair(*piece, rng.gen()).into();
+                match rng.gen_range(0..3) {
+                    0 => havings.push(ch),
+                    1 => {
+                        let prefix = if rng.gen() && *piece != Piece::Kin {
+                            "+"
+                        } else {
+                            ""
+                        };
+                        board[squares.next().unwrap()] = format!("{}{}", prefix, ch);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for rank in board.chunks(9) {
+            let mut empty = 0;
+            for square in rank {
+                if square.is_empty() {
+                    empty += 1;
+                } else {
+                    if empty != 0 {
+                        sfen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    sfen.push_str(square);
+                }
+            }
+            if empty != 0 {
+                sfen.push_str(&empty.to_string());
+            }
+            sfen.push('/');
+        }
+        sfen.pop();
+        let mut counted = String::new();
+        for ch in [
+            'R', 'B', 'G', 'S', 'N', 'L', 'P', 'r', 'b', 'g', 's', 'n', 'l', 'p',
+        ] {
+            match havings.chars().filter(|c| *c == ch).count() {
+                0 => {}
+                1 => counted.push(ch),
+                n => counted.push_str(&format!("{}{}", n, ch)),
+            }
+        }
+        if counted.is_empty() {
+            counted.push('-');
+        }
+        let sfen = format!(
+            "{} {} {} {}",
+            sfen,
+            if rng.gen() { 'b' } else { 'w' },
+            counted,
+            rng.gen_range(1..1000)
+        );
+
+        let ban = Ban2::from_sfen(&sfen).unwrap();
+        assert_eq!(ban.to_sfen(), sfen);
+        let again = Ban2::from_sfen(&ban.to_sfen()).unwrap();
+        assert_eq!(again, ban);
+    }
+}
+
+#[test]
+fn canonical_sfen() {
+    let mut ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b 2PGR2pbl 1").unwrap();
+    let mut other = ban.clone();
+    other.primary_pieces.reverse();
+    other.secondary_pieces.rotate_left(2);
+    assert_eq!(ban, other);
+    assert_eq!(ban.to_sfen(), "4k4/9/9/9/9/9/9/9/4K4 b RG2Pbl2p 1");
+    assert_eq!(other.to_sfen(), ban.to_sfen());
+
+    ban.put_piece(Piece::Kin, 5, 5).unwrap();
+    ban.move_piece(5, 1, 4, 1, false).unwrap();
+    assert_eq!(ban.to_sfen(), "5k3/9/9/9/4G4/9/9/9/4K4 b R2Pbl2p 3");
+}
+
+#[test]
+fn validate() {
+    assert_eq!(Ban2::new().validate(), Ok(()));
+
+    let ban = Ban2::from_sfen("P3k3P/9/4P4/9/9/4P4/9/4L4/9 w - 1").unwrap();
+    assert_eq!(
+        ban.validate(),
+        Err(vec![
+            Violation::NoOu { turn: true },
+            Violation::Nifu { turn: true, x: 5 },
+            Violation::NoNextMove { x: 1, y: 1 },
+            Violation::NoNextMove { x: 9, y: 1 },
+        ])
+    );
+
+    let ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/4R4/4K4 b - 1").unwrap();
+    assert_eq!(ban.validate(), Err(vec![Violation::OpponentChecked]));
+    assert!(matches!(
+        Ban2::from_sfen_validated("4k4/9/9/9/9/9/9/4R4/4K4 b - 1"),
+        Err(Error::InvalidPosition(_))
+    ));
+}
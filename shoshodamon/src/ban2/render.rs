@@ -0,0 +1,402 @@
+//! Human readable rendering of boards
+
+use std::fmt;
+
+use super::{Ban2, HAVING_ORDER};
+use crate::{
+    notation::japanese::{self, KANJI_DIGITS, ZENKAKU_DIGITS},
+    Hand, OnBoardPiece, Piece,
+};
+
+/// One letter names of pieces used on a kanji board
+const KANJI_PIECES: [(char, Piece, bool); 14] = [
+    ('歩', Piece::Fu, false),
+    ('香', Piece::Kyosha, false),
+    ('桂', Piece::Keima, false),
+    ('銀', Piece::Gin, false),
+    ('金', Piece::Kin, false),
+    ('角', Piece::Kaku, false),
+    ('飛', Piece::Hisha, false),
+    ('玉', Piece::Ou, false),
+    ('と', Piece::Fu, true),
+    ('杏', Piece::Kyosha, true),
+    ('圭', Piece::Keima, true),
+    ('全', Piece::Gin, true),
+    ('馬', Piece::Kaku, true),
+    ('龍', Piece::Hisha, true),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Sfen letters with file numbers and rank letters
+    Ascii,
+    /// Kanji board in BOD style, where gote pieces are marked with `v`
+    Kanji,
+}
+
+/// Renderer of boards, `Display` of `Ban2` uses the default ascii one
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub style: Style,
+    /// Write file and rank labels around an ascii board, kanji boards always have them
+    pub labels: bool,
+    /// Highlight the squares of the last move
+    pub last_move: Option<Hand>,
+}
+
+impl Renderer {
+    pub fn ascii() -> Self {
+        Self {
+            style: Style::Ascii,
+            labels: true,
+            last_move: None,
+        }
+    }
+
+    pub fn kanji() -> Self {
+        Self {
+            style: Style::Kanji,
+            ..Self::ascii()
+        }
+    }
+
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn last_move(mut self, hand: Option<&Hand>) -> Self {
+        self.last_move = hand.cloned();
+        self
+    }
+
+    pub fn render(&self, ban: &Ban2) -> String {
+        match self.style {
+            Style::Ascii => self.render_ascii(ban),
+            Style::Kanji => self.render_kanji(ban),
+        }
+    }
+
+    /// Check given square is the origin or the destination of the last move
+    fn is_highlighted(&self, x: usize, y: usize) -> bool {
+        match &self.last_move {
+            Some(hand @ Hand::Movement { x: ox, y: oy, .. }) => {
+                (*ox, *oy) == (x, y) || hand.destination() == (x, y)
+            }
+            Some(hand @ Hand::Putting { .. }) => hand.destination() == (x, y),
+            None => false,
+        }
+    }
+
+    fn render_ascii(&self, ban: &Ban2) -> String {
+        let mut out = String::new();
+        if self.labels {
+            out.push(' ');
+            (1..=9)
+                .rev()
+                .for_each(|x| out.push_str(&format!("  {}", x)));
+            out.push('\n');
+        }
+        out.push_str("+---------------------------+\n");
+        for y in 1..=9 {
+            out.push('|');
+            for x in (1..=9).rev() {
+                let mut cell = match ban.get_position(x, y) {
+                    Some(piece) => String::from(piece),
+                    None => ".".to_string(),
+                };
+                if self.is_highlighted(x, y) {
+                    cell.insert(0, '*');
+                }
+                out.push_str(&format!("{:>3}", cell));
+            }
+            out.push('|');
+            if self.labels {
+                out.push_str(&format!(" {}", (b'a' + y as u8 - 1) as char));
+            }
+            out.push('\n');
+        }
+        out.push_str("+---------------------------+\n");
+
+        for (name, pieces) in [
+            ("Sente", &ban.primary_pieces),
+            ("Gote", &ban.secondary_pieces),
+        ] {
+            let having = havings(pieces)
+                .map(|(piece, count)| {
+                    let letter: char = crate::PieceBoolPair(piece, true).into();
+                    if count > 1 {
+                        format!("{}{}", letter, count)
+                    } else {
+                        letter.to_string()
+                    }
+                })
+                .collect::<Vec<_>>();
+            let having = if having.is_empty() {
+                "-".to_string()
+            } else {
+                having.join(" ")
+            };
+            out.push_str(&format!("{} hand: {}\n", name, having));
+        }
+        if let Some(hand) = &self.last_move {
+            out.push_str(&format!("Last move: {}\n", String::from(hand.clone())));
+        }
+        out.push_str(if ban.turn {
+            "Turn: sente\n"
+        } else {
+            "Turn: gote\n"
+        });
+        out
+    }
+
+    fn render_kanji(&self, ban: &Ban2) -> String {
+        let mut out = format!("後手の持駒：{}\n", kanji_havings(&ban.secondary_pieces));
+        out.push(' ');
+        ZENKAKU_DIGITS
+            .iter()
+            .rev()
+            .for_each(|d| out.push_str(&format!(" {}", d)));
+        out.push('\n');
+        out.push_str("+---------------------------+\n");
+        for y in 1..=9 {
+            out.push('|');
+            for x in (1..=9).rev() {
+                match ban.get_position(x, y) {
+                    Some(piece) => {
+                        out.push(if piece.turn { ' ' } else { 'v' });
+                        out.push(kanji_piece(piece));
+                    }
+                    None => out.push_str(" ・"),
+                }
+            }
+            out.push('|');
+            out.push(KANJI_DIGITS[y - 1]);
+            out.push('\n');
+        }
+        out.push_str("+---------------------------+\n");
+        out.push_str(&format!(
+            "先手の持駒：{}\n",
+            kanji_havings(&ban.primary_pieces)
+        ));
+        if let Some(hand) = &self.last_move {
+            out.push_str(&format!(
+                "手数＝{}  {}{}  まで\n",
+                ban.ply.saturating_sub(1),
+                if ban.turn { '△' } else { '▲' },
+                last_move_name(ban, hand)
+            ));
+        } else if ban.ply > 1 {
+            out.push_str(&format!("手数＝{}\n", ban.ply - 1));
+        }
+        out.push_str(if ban.turn {
+            "先手番\n"
+        } else {
+            "後手番\n"
+        });
+        out
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::ascii()
+    }
+}
+
+/// Ascii board by default, kanji board with `{:#}`
+impl fmt::Display for Ban2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let renderer = if f.alternate() {
+            Renderer::kanji()
+        } else {
+            Renderer::ascii()
+        };
+        f.write_str(&renderer.render(self))
+    }
+}
+
+/// Count pieces in hand in the order of sfen havings
+pub(super) fn havings(pieces: &[Piece]) -> impl Iterator<Item = (Piece, usize)> + '_ {
+    HAVING_ORDER.iter().filter_map(move |(piece, _)| {
+        let count = pieces.iter().filter(|p| *p == piece).count();
+        (count > 0).then_some((*piece, count))
+    })
+}
+
+pub(super) fn kanji_piece(piece: &OnBoardPiece) -> char {
+    KANJI_PIECES
+        .iter()
+        .find(|(_, p, promoted)| *p == piece.piece && *promoted == piece.promoted)
+        .map(|(c, _, _)| *c)
+        .unwrap()
+}
+
+/// Number in kanji like `九`, `十八` or `二十三`, and `〇` for zero
+pub(super) fn kanji_number(n: usize) -> String {
+    if n == 0 {
+        return "〇".to_string();
+    }
+    let mut s = if n >= 10000 {
+        format!("{}万", kanji_number(n / 10000))
+    } else {
+        String::new()
+    };
+    for (unit, name) in [(1000, '千'), (100, '百'), (10, '十')] {
+        let digit = n / unit % 10;
+        if digit > 1 {
+            s.push(KANJI_DIGITS[digit - 1]);
+        }
+        if digit > 0 {
+            s.push(name);
+        }
+    }
+    let ones = n % 10;
+    if ones > 0 {
+        s.push(KANJI_DIGITS[ones - 1]);
+    }
+    s
+}
+
+/// Pieces in hand like `飛　歩二　`, or `なし`
+fn kanji_havings(pieces: &[Piece]) -> String {
+    let having = havings(pieces)
+        .map(|(piece, count)| {
+            let name = kanji_piece(&OnBoardPiece {
+                piece,
+                promoted: false,
+                turn: true,
+            });
+            if count > 1 {
+                format!("{}{}　", name, kanji_number(count))
+            } else {
+                format!("{}　", name)
+            }
+        })
+        .collect::<String>();
+    if having.is_empty() {
+        "なし".to_string()
+    } else {
+        having
+    }
+}
+
+/// Name the last move from the board after it, like `７六歩` or `２二角成`
+fn last_move_name(ban: &Ban2, hand: &Hand) -> String {
+    let dest = hand.destination();
+    let destination = japanese::format_destination(dest, None);
+    match (hand, ban.get_position(dest.0, dest.1)) {
+        (Hand::Putting { piece, .. }, _) => {
+            format!("{}{}打", destination, japanese::piece_name(*piece, false))
+        }
+        (
+            Hand::Movement {
+                with_promote: true, ..
+            },
+            Some(piece),
+        ) => format!(
+            "{}{}成",
+            destination,
+            japanese::piece_name(piece.piece, false)
+        ),
+        (_, Some(piece)) => format!(
+            "{}{}",
+            destination,
+            japanese::piece_name(piece.piece, piece.promoted)
+        ),
+        (_, None) => destination,
+    }
+}
+
+#[test]
+fn ascii_board() {
+    use std::convert::TryInto;
+
+    let mut ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b 2PGr 1").unwrap();
+    let hand: Hand = "G*5h".try_into().unwrap();
+    ban.apply_hand(&hand).unwrap();
+    let expected = "   9  8  7  6  5  4  3  2  1
++---------------------------+
+|  .  .  .  .  k  .  .  .  .| a
+|  .  .  .  .  .  .  .  .  .| b
+|  .  .  .  .  .  .  .  .  .| c
+|  .  .  .  .  .  .  .  .  .| d
+|  .  .  .  .  .  .  .  .  .| e
+|  .  .  .  .  .  .  .  .  .| f
+|  .  .  .  .  .  .  .  .  .| g
+|  .  .  .  . *G  .  .  .  .| h
+|  .  .  .  .  K  .  .  .  .| i
++---------------------------+
+Sente hand: P2
+Gote hand: R
+Last move: G*5h
+Turn: gote
+";
+    assert_eq!(
+        Renderer::ascii().last_move(Some(&hand)).render(&ban),
+        expected
+    );
+
+    let ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/+B3K4 b - 1").unwrap();
+    let board = Renderer::ascii().labels(false).render(&ban);
+    assert!(board.starts_with("+---"));
+    assert!(board.contains("\n| +B  .  .  .  K  .  .  .  .|\n"));
+    assert_eq!(ban.to_string(), Renderer::default().render(&ban));
+}
+
+#[test]
+fn kanji_board() {
+    use std::convert::TryInto;
+
+    let mut ban =
+        Ban2::from_sfen("lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2")
+            .unwrap();
+    let hand: Hand = "3c3d".try_into().unwrap();
+    ban.apply_hand(&hand).unwrap();
+    let expected = "後手の持駒：なし
+  ９ ８ ７ ６ ５ ４ ３ ２ １
++---------------------------+
+|v香v桂v銀v金v玉v金v銀v桂v香|一
+| ・v飛 ・ ・ ・ ・ ・v角 ・|二
+|v歩v歩v歩v歩v歩v歩 ・v歩v歩|三
+| ・ ・ ・ ・ ・ ・v歩 ・ ・|四
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|五
+| ・ ・ 歩 ・ ・ ・ ・ ・ ・|六
+| 歩 歩 ・ 歩 歩 歩 歩 歩 歩|七
+| ・ 角 ・ ・ ・ ・ ・ 飛 ・|八
+| 香 桂 銀 金 玉 金 銀 桂 香|九
++---------------------------+
+先手の持駒：なし
+手数＝2  △３四歩  まで
+先手番
+";
+    assert_eq!(
+        Renderer::kanji().last_move(Some(&hand)).render(&ban),
+        expected
+    );
+    assert_eq!(format!("{:#}", ban), Renderer::kanji().render(&ban));
+
+    let ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/+B3K3+r b RG18P 1").unwrap();
+    let board = Renderer::kanji().render(&ban);
+    assert!(board.starts_with("後手の持駒：なし\n"));
+    assert!(board.contains("\n| 馬 ・ ・ ・ 玉 ・ ・ ・v龍|九\n"));
+    assert!(board.contains("\n先手の持駒：飛　金　歩十八　\n"));
+}
+
+#[test]
+fn kanji_numbers() {
+    let cases = [
+        (0, "〇"),
+        (1, "一"),
+        (10, "十"),
+        (18, "十八"),
+        (20, "二十"),
+        (23, "二十三"),
+        (100, "百"),
+        (1204, "千二百四"),
+        (30000, "三万"),
+    ];
+    for (n, kanji) in cases.iter() {
+        assert_eq!(kanji_number(*n), *kanji);
+    }
+}
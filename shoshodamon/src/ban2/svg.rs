@@ -0,0 +1,341 @@
+//! Standalone SVG images of boards
+
+use super::{
+    render::{havings, kanji_number, kanji_piece},
+    Ban2,
+};
+use crate::{
+    notation::{japanese::KANJI_DIGITS, western::piece_letter},
+    Hand, OnBoardPiece,
+};
+
+/// Size of a square
+const SQUARE: usize = 40;
+/// Width of the columns of pieces in hand on both sides
+const HAND_WIDTH: usize = 50;
+const BOARD_LEFT: usize = HAND_WIDTH + 10;
+const BOARD_TOP: usize = 30;
+const BOARD_SIZE: usize = SQUARE * 9;
+const WIDTH: usize = BOARD_LEFT + BOARD_SIZE + 30 + HAND_WIDTH + 10;
+const HEIGHT: usize = BOARD_TOP + BOARD_SIZE + 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs {
+    /// One letter kanji like `歩` or `と`
+    Kanji,
+    /// Letters like `P` or `+P`
+    Western,
+}
+
+/// Renderer of boards as SVG images, gote pieces are rotated
+#[derive(Debug, Clone)]
+pub struct SvgRenderer {
+    pub glyphs: Glyphs,
+    /// Write file numbers and rank labels around the board
+    pub coordinates: bool,
+    /// Highlighted squares as (x, y), squares off the board are not drawn
+    pub highlights: Vec<(usize, usize)>,
+    /// Moves drawn as arrows, moves from or to squares off the board are not drawn
+    pub arrows: Vec<Hand>,
+}
+
+impl SvgRenderer {
+    pub fn kanji() -> Self {
+        Self {
+            glyphs: Glyphs::Kanji,
+            coordinates: true,
+            highlights: Vec::new(),
+            arrows: Vec::new(),
+        }
+    }
+
+    pub fn western() -> Self {
+        Self {
+            glyphs: Glyphs::Western,
+            ..Self::kanji()
+        }
+    }
+
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Highlight given square, ignored when it is off the board
+    pub fn highlight(mut self, x: usize, y: usize) -> Self {
+        if on_board(x, y) {
+            self.highlights.push((x, y));
+        }
+        self
+    }
+
+    /// Highlight the origin and the destination of given move
+    pub fn highlight_hand(self, hand: &Hand) -> Self {
+        let (dx, dy) = hand.destination();
+        match *hand {
+            Hand::Movement { x, y, .. } => self.highlight(x, y).highlight(dx, dy),
+            Hand::Putting { .. } => self.highlight(dx, dy),
+        }
+    }
+
+    /// Draw given move as an arrow, ignored when it is a drop or leaves the board
+    pub fn arrow(mut self, hand: &Hand) -> Self {
+        if arrow_ends(hand).is_some() {
+            self.arrows.push(hand.clone());
+        }
+        self
+    }
+
+    pub fn render(&self, ban: &Ban2) -> String {
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n",
+            w = WIDTH,
+            h = HEIGHT
+        );
+        out.push_str("<defs><marker id=\"arrowhead\" markerWidth=\"6\" markerHeight=\"6\" refX=\"3\" refY=\"3\" orient=\"auto\"><path d=\"M0,0 L6,3 L0,6 Z\" fill=\"#d33\"/></marker></defs>\n");
+        out.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"#f5d9a3\" stroke=\"#000\" stroke-width=\"2\"/>\n",
+            BOARD_LEFT,
+            BOARD_TOP,
+            s = BOARD_SIZE
+        ));
+
+        for &(x, y) in self.highlights.iter().filter(|(x, y)| on_board(*x, *y)) {
+            let (left, top) = square_origin(x, y);
+            out.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" fill=\"#ff0\" fill-opacity=\"0.5\"/>\n",
+                left,
+                top,
+                s = SQUARE
+            ));
+        }
+        for i in 1..9 {
+            let offset = i * SQUARE;
+            out.push_str(&format!(
+                "<line x1=\"{x}\" y1=\"{}\" x2=\"{x}\" y2=\"{}\" stroke=\"#000\"/>\n",
+                BOARD_TOP,
+                BOARD_TOP + BOARD_SIZE,
+                x = BOARD_LEFT + offset
+            ));
+            out.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{y}\" x2=\"{}\" y2=\"{y}\" stroke=\"#000\"/>\n",
+                BOARD_LEFT,
+                BOARD_LEFT + BOARD_SIZE,
+                y = BOARD_TOP + offset
+            ));
+        }
+        if self.coordinates {
+            self.render_coordinates(&mut out);
+        }
+
+        for x in 1..=9 {
+            for y in 1..=9 {
+                if let Some(piece) = ban.get_position(x, y) {
+                    let (cx, cy) = square_center(x, y);
+                    out.push_str(&self.text(
+                        &self.glyph(piece),
+                        cx,
+                        cy,
+                        piece.turn,
+                        piece.promoted,
+                    ));
+                }
+            }
+        }
+        self.render_hand(ban, true, &mut out);
+        self.render_hand(ban, false, &mut out);
+
+        for hand in &self.arrows {
+            if let Some(((x, y), (dx, dy))) = arrow_ends(hand) {
+                let (x1, y1) = square_center(x, y);
+                let (x2, y2) = square_center(dx, dy);
+                out.push_str(&format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#d33\" stroke-width=\"4\" stroke-opacity=\"0.8\" marker-end=\"url(#arrowhead)\"/>\n",
+                    x1, y1, x2, y2
+                ));
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    fn render_coordinates(&self, out: &mut String) {
+        for x in 1..=9 {
+            let (cx, _) = square_center(x, 1);
+            out.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"14\" text-anchor=\"middle\">{}</text>\n",
+                cx,
+                BOARD_TOP - 8,
+                x
+            ));
+        }
+        for y in 1..=9 {
+            let (_, cy) = square_center(1, y);
+            let label = match self.glyphs {
+                Glyphs::Kanji => KANJI_DIGITS[y - 1],
+                Glyphs::Western => (b'a' + y as u8 - 1) as char,
+            };
+            out.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"14\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                BOARD_LEFT + BOARD_SIZE + 12,
+                cy,
+                label
+            ));
+        }
+    }
+
+    /// Pieces in hand, sente ones at the right from the top and gote ones at the left from
+    /// the bottom
+    fn render_hand(&self, ban: &Ban2, turn: bool, out: &mut String) {
+        let pieces = if turn {
+            &ban.primary_pieces
+        } else {
+            &ban.secondary_pieces
+        };
+        let cx = if turn {
+            BOARD_LEFT + BOARD_SIZE + 30 + HAND_WIDTH / 2
+        } else {
+            HAND_WIDTH / 2
+        };
+        let mark = if turn { "☗" } else { "☖" };
+        let mut items = vec![mark.to_string()];
+        for (piece, count) in havings(pieces) {
+            let name = self.glyph(&OnBoardPiece {
+                piece,
+                promoted: false,
+                turn,
+            });
+            items.push(match (count, self.glyphs) {
+                (1, _) => name,
+                (_, Glyphs::Kanji) => format!("{}{}", name, kanji_number(count)),
+                (_, Glyphs::Western) => format!("{}{}", name, count),
+            });
+        }
+        for (i, item) in items.iter().enumerate() {
+            let offset = SQUARE * i + SQUARE / 2;
+            let cy = if turn {
+                BOARD_TOP + offset
+            } else {
+                BOARD_TOP + BOARD_SIZE - offset
+            };
+            out.push_str(&self.text(item, cx, cy, turn, false));
+        }
+    }
+
+    fn glyph(&self, piece: &OnBoardPiece) -> String {
+        match self.glyphs {
+            Glyphs::Kanji => kanji_piece(piece).to_string(),
+            Glyphs::Western if piece.promoted => format!("+{}", piece_letter(piece.piece)),
+            Glyphs::Western => piece_letter(piece.piece).to_string(),
+        }
+    }
+
+    /// A text centered at given point, rotated for gote and red for promoted pieces
+    fn text(&self, text: &str, cx: usize, cy: usize, turn: bool, promoted: bool) -> String {
+        let size = match self.glyphs {
+            Glyphs::Kanji if text.chars().count() == 1 => 28,
+            _ => 20,
+        };
+        let rotate = if turn {
+            String::new()
+        } else {
+            format!(" transform=\"rotate(180 {} {})\"", cx, cy)
+        };
+        let fill = if promoted { "#c00" } else { "#000" };
+        format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\"{}>{}</text>\n",
+            cx, cy, size, fill, rotate, text
+        )
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::kanji()
+    }
+}
+
+/// Check given square is on the board
+fn on_board(x: usize, y: usize) -> bool {
+    (1..=9).contains(&x) && (1..=9).contains(&y)
+}
+
+/// Get the origin and the destination of a move on the board
+fn arrow_ends(hand: &Hand) -> Option<((usize, usize), (usize, usize))> {
+    match *hand {
+        Hand::Movement { x, y, dx, dy, .. } => {
+            let (ax, ay) = (x as isize + dx, y as isize + dy);
+            if ax < 1 || ay < 1 || !on_board(x, y) || !on_board(ax as usize, ay as usize) {
+                return None;
+            }
+            Some(((x, y), (ax as usize, ay as usize)))
+        }
+        Hand::Putting { .. } => None,
+    }
+}
+
+/// Top left corner of given square
+fn square_origin(x: usize, y: usize) -> (usize, usize) {
+    (BOARD_LEFT + (9 - x) * SQUARE, BOARD_TOP + (y - 1) * SQUARE)
+}
+
+fn square_center(x: usize, y: usize) -> (usize, usize) {
+    let (left, top) = square_origin(x, y);
+    (left + SQUARE / 2, top + SQUARE / 2)
+}
+
+#[test]
+fn svg_board() {
+    use std::convert::TryInto;
+
+    let ban = Ban2::new();
+    let svg = SvgRenderer::kanji().render(&ban);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches(">歩</text>").count(), 18);
+    // 20 gote pieces and the mark of gote hand
+    assert_eq!(svg.matches("rotate(180").count(), 21);
+    assert!(svg.contains(">九</text>"));
+    assert!(!svg.contains("marker-end"));
+
+    let hand: Hand = "7g7f".try_into().unwrap();
+    let mut moved = ban.clone();
+    moved.apply_hand(&hand).unwrap();
+    let svg = SvgRenderer::western()
+        .coordinates(false)
+        .highlight_hand(&hand)
+        .arrow(&hand)
+        .render(&moved);
+    assert_eq!(svg.matches(">P</text>").count(), 18);
+    assert_eq!(svg.matches("fill=\"#ff0\"").count(), 2);
+    // from the center of 7g to the one of 7f
+    assert!(svg.contains("<line x1=\"160\" y1=\"290\" x2=\"160\" y2=\"250\""));
+    assert!(!svg.contains(">i</text>"));
+
+    // squares off the board are skipped
+    let off = Hand::Movement {
+        x: 1,
+        y: 1,
+        dx: -1,
+        dy: 0,
+        with_promote: false,
+    };
+    let mut renderer = SvgRenderer::kanji()
+        .highlight(10, 1)
+        .highlight(0, 5)
+        .highlight_hand(&off)
+        .arrow(&off);
+    assert_eq!(renderer.highlights, vec![(1, 1)]);
+    assert!(renderer.arrows.is_empty());
+    renderer.highlights.push((12, 3));
+    renderer.arrows.push(off);
+    let svg = renderer.render(&ban);
+    assert_eq!(svg.matches("fill=\"#ff0\"").count(), 1);
+    assert!(!svg.contains("marker-end"));
+
+    let ban = Ban2::from_sfen("4k4/9/9/9/9/9/9/9/+B3K4 b R2Pg 1").unwrap();
+    let svg = SvgRenderer::kanji().render(&ban);
+    assert!(svg.contains("fill=\"#c00\">馬</text>"));
+    assert!(svg.contains(">歩二</text>"));
+    assert!(svg.contains("rotate(180 25 330)\">金</text>"));
+}
@@ -0,0 +1,499 @@
+//! Opening books in the YaneuraOu `#YANEURAOU-DB2016` format, e.g.
+//! ```text
+//! #YANEURAOU-DB2016 1.00
+//! sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1
+//! 7g7f 3c3d 30 20 120
+//! 2g2f none 25 20 80
+//! ```
+//! where a move has its ponder move, value, depth and count. Positions are looked up without
+//! the move count of sfen.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    fs,
+    path::Path,
+};
+
+use rand::Rng;
+
+use crate::{
+    ban2::Ban2 as Ban,
+    error::Error,
+    record::{GameRecord, GameResult},
+    Hand,
+};
+
+const HEADER: &str = "#YANEURAOU-DB2016 1.00";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookMove {
+    pub hand: Hand,
+    /// Expected reply of the opponent
+    pub ponder: Option<Hand>,
+    /// Evaluation in centipawns for the side to move
+    pub value: isize,
+    pub depth: usize,
+    /// How many times the move was played, used as the weight of random selection
+    pub count: usize,
+}
+
+/// How to select a move from the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The move with the highest value, and the most played one among them
+    Best,
+    /// A random move weighted by counts among the moves whose values are within `margin`
+    /// centipawns of the best one
+    Weighted { margin: isize },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Entry {
+    /// Move count written in sfen
+    ply: usize,
+    moves: Vec<BookMove>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    /// Entries keyed by sfen without its move count
+    entries: BTreeMap<String, Entry>,
+}
+
+/// Get sfen without the move count
+fn key(ban: &Ban) -> String {
+    let sfen = ban.to_sfen();
+    match sfen.rsplit_once(' ') {
+        Some((key, _)) => key.to_string(),
+        None => sfen,
+    }
+}
+
+/// Sort moves from the best one, by values and then counts
+fn sort_moves(moves: &mut [BookMove]) {
+    moves.sort_by_key(|mv| (-mv.value, std::cmp::Reverse(mv.count)));
+}
+
+fn book_error(line: usize, message: impl std::fmt::Display) -> Error {
+    Error::Book(format!("{} (at line {})", message, line + 1))
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(book: &str) -> Result<Self, Error> {
+        let mut result = Self::new();
+        let mut current: Option<String> = None;
+        for (i, line) in book.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            if let Some(sfen) = line.strip_prefix("sfen ") {
+                let ban = Ban::from_sfen(sfen.trim()).map_err(|e| book_error(i, e))?;
+                let key = key(&ban);
+                result.entries.entry(key.clone()).or_default().ply = ban.ply;
+                current = Some(key);
+                continue;
+            }
+            let key = current
+                .as_ref()
+                .ok_or_else(|| book_error(i, "move before any sfen"))?;
+            let mv = parse_move(line).map_err(|e| book_error(i, e))?;
+            result.entries.get_mut(key).unwrap().moves.push(mv);
+        }
+        result
+            .entries
+            .values_mut()
+            .for_each(|entry| sort_moves(&mut entry.moves));
+        Ok(result)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Write the book, where positions are sorted by sfen
+    pub fn write(&self) -> String {
+        let mut out = format!("{}\n", HEADER);
+        for (key, entry) in &self.entries {
+            out.push_str(&format!("sfen {} {}\n", key, entry.ply));
+            for mv in &entry.moves {
+                let ponder = match &mv.ponder {
+                    Some(hand) => String::from(hand.clone()),
+                    None => "none".to_string(),
+                };
+                out.push_str(&format!(
+                    "{} {} {} {} {}\n",
+                    String::from(mv.hand.clone()),
+                    ponder,
+                    mv.value,
+                    mv.depth,
+                    mv.count
+                ));
+            }
+        }
+        out
+    }
+
+    /// Count positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the moves of given position, which may contain illegal ones from broken books
+    pub fn moves(&self, ban: &Ban) -> &[BookMove] {
+        self.entries
+            .get(&key(ban))
+            .map_or(&[], |entry| entry.moves.as_slice())
+    }
+
+    /// Add a move to given position, replacing the same move
+    pub fn add(&mut self, ban: &Ban, mv: BookMove) {
+        let entry = self.entries.entry(key(ban)).or_default();
+        entry.ply = ban.ply;
+        match entry.moves.iter_mut().find(|m| m.hand == mv.hand) {
+            Some(m) => *m = mv,
+            None => entry.moves.push(mv),
+        }
+        sort_moves(&mut entry.moves);
+    }
+
+    /// Select a legal move of given position
+    pub fn probe<R: Rng + ?Sized>(
+        &self,
+        ban: &Ban,
+        selection: Selection,
+        rng: &mut R,
+    ) -> Option<&BookMove> {
+        let moves = self
+            .moves(ban)
+            .iter()
+            .filter(|mv| ban.clone().apply_hand(&mv.hand).is_ok())
+            .collect::<Vec<_>>();
+        let best = moves
+            .iter()
+            .copied()
+            .max_by_key(|mv| (mv.value, mv.count))?;
+        match selection {
+            Selection::Best => Some(best),
+            Selection::Weighted { margin } => {
+                let candidates = moves
+                    .into_iter()
+                    .filter(|mv| mv.value >= best.value - margin)
+                    .collect::<Vec<_>>();
+                let total = candidates.iter().map(|mv| mv.count.max(1)).sum::<usize>();
+                let mut roll = rng.gen_range(0..total);
+                candidates.into_iter().find(|mv| {
+                    let weight = mv.count.max(1);
+                    if roll < weight {
+                        true
+                    } else {
+                        roll -= weight;
+                        false
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Statistics of a move in game records, where wins and draws are counted from the side to move
+#[derive(Debug, Clone, Default)]
+struct MoveStats {
+    count: usize,
+    wins: usize,
+    draws: usize,
+    /// Games with known outcomes
+    decided: usize,
+}
+
+impl MoveStats {
+    /// Convert the smoothed win rate to centipawns, in the same scale as `1 / (1 + e^(-cp/600))`
+    fn value(&self) -> isize {
+        let rate = (self.wins * 2 + self.draws + 2) as f64 / ((self.decided + 2) * 2) as f64;
+        (600.0 * (rate / (1.0 - rate)).ln()).round() as isize
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PositionStats {
+    ban: Ban,
+    moves: Vec<(Hand, MoveStats)>,
+}
+
+/// Builds a book from the main lines of game records, counting moves and their win rates
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    /// Statistics keyed like `Book`
+    positions: HashMap<String, PositionStats>,
+}
+
+impl BookBuilder {
+    /// Count only the first `max_ply` moves of each game
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn add_record(&mut self, record: &GameRecord) {
+        let winner = record.winner();
+        let decided = winner.is_some()
+            || matches!(
+                record.result,
+                Some(GameResult::Sennichite) | Some(GameResult::Jishogi)
+            );
+        let mut ban = record.initial.clone();
+        for mv in record.moves.iter().take(self.max_ply) {
+            let position = self
+                .positions
+                .entry(key(&ban))
+                .or_insert_with(|| PositionStats {
+                    ban: ban.clone(),
+                    moves: Vec::new(),
+                });
+            let index = match position.moves.iter().position(|(h, _)| *h == mv.hand) {
+                Some(index) => index,
+                None => {
+                    position.moves.push((mv.hand.clone(), MoveStats::default()));
+                    position.moves.len() - 1
+                }
+            };
+            let stats = &mut position.moves[index].1;
+            stats.count += 1;
+            if decided {
+                stats.decided += 1;
+                match winner {
+                    Some(winner) if winner == ban.turn => stats.wins += 1,
+                    Some(_) => {}
+                    None => stats.draws += 1,
+                }
+            }
+            if ban.apply_hand(&mv.hand).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Count positions seen
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Make a book of the moves played at least `min_count` times. `score` may evaluate a move
+    /// as (value, depth) for the side to move, otherwise the value comes from its win rate
+    pub fn build(
+        &self,
+        min_count: usize,
+        mut score: impl FnMut(&Ban, &Hand) -> Option<(isize, usize)>,
+    ) -> Book {
+        let mut book = Book::new();
+        for position in self.positions.values() {
+            for (hand, stats) in &position.moves {
+                if stats.count < min_count {
+                    continue;
+                }
+                let mut next = position.ban.clone();
+                if next.apply_hand(hand).is_err() {
+                    continue;
+                }
+                let ponder = self.positions.get(&key(&next)).and_then(|p| {
+                    p.moves
+                        .iter()
+                        .filter(|(_, s)| s.count >= min_count)
+                        .max_by_key(|(_, s)| s.count)
+                        .map(|(h, _)| h.clone())
+                });
+                let (value, depth) = score(&position.ban, hand).unwrap_or((stats.value(), 0));
+                book.add(
+                    &position.ban,
+                    BookMove {
+                        hand: hand.clone(),
+                        ponder,
+                        value,
+                        depth,
+                        count: stats.count,
+                    },
+                );
+            }
+        }
+        book
+    }
+}
+
+/// Parse a move line like `7g7f 3c3d 30 20 120`, where numbers may be omitted
+fn parse_move(line: &str) -> Result<BookMove, String> {
+    let mut tokens = line.split_ascii_whitespace();
+    let hand: Hand = tokens
+        .next()
+        .unwrap()
+        .try_into()
+        .map_err(|e: Error| e.to_string())?;
+    let ponder = match tokens.next() {
+        None | Some("none") => None,
+        Some(s) => Some(s.try_into().map_err(|e: Error| e.to_string())?),
+    };
+    let mut number = |name: &str| {
+        tokens.next().map_or(Ok(0), |s| {
+            s.parse::<isize>()
+                .map_err(|_| format!("invalid {} {}", name, s))
+        })
+    };
+    let value = number("value")?;
+    let depth = number("depth")?;
+    let count = number("count")?;
+    if depth < 0 || count < 0 {
+        return Err(format!("negative depth or count in {}", line));
+    }
+    Ok(BookMove {
+        hand,
+        ponder,
+        value,
+        depth: depth as usize,
+        count: count as usize,
+    })
+}
+
+#[test]
+fn book() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let text = "#YANEURAOU-DB2016 1.00
+sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1
+7g7f 3c3d 30 20 120
+2g2f none 30 20 80
+5i5h none -200 20 1000
+5g5f none 0 10
+sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2
+3c3d 2g2f 0 18 10
+";
+    let book = Book::parse(text).unwrap();
+    assert_eq!(book.len(), 2);
+    let mut ban = Ban::new();
+    // the move count is ignored
+    ban.ply = 5;
+    assert_eq!(book.moves(&ban).len(), 4);
+    assert_eq!(book.moves(&ban)[2].count, 0);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let best = book.probe(&ban, Selection::Best, &mut rng).unwrap();
+    assert_eq!(String::from(best.hand.clone()), "7g7f");
+    assert_eq!(best.ponder, Some("3c3d".try_into().unwrap()));
+
+    let mut counts = std::collections::HashMap::new();
+    for _ in 0..1000 {
+        let mv = book
+            .probe(&ban, Selection::Weighted { margin: 50 }, &mut rng)
+            .unwrap();
+        *counts.entry(String::from(mv.hand.clone())).or_insert(0) += 1;
+    }
+    assert_eq!(counts.get("5i5h"), None);
+    assert!(counts["7g7f"] > counts["2g2f"]);
+    assert!(counts["5g5f"] > 0);
+
+    ban.apply_hand(&"7g7f".try_into().unwrap()).unwrap();
+    ban.apply_hand(&"3c3d".try_into().unwrap()).unwrap();
+    assert_eq!(book.probe(&ban, Selection::Best, &mut rng), None);
+
+    let written = book.write();
+    assert!(written.starts_with("#YANEURAOU-DB2016 1.00\nsfen "));
+    assert!(written.contains("\n5g5f none 0 10 0\n"));
+    assert_eq!(Book::parse(&written), Ok(book));
+}
+
+#[test]
+fn book_add() {
+    let mut book = Book::new();
+    let ban = Ban::new();
+    let mv = |hand: &str, value| BookMove {
+        hand: hand.try_into().unwrap(),
+        ponder: None,
+        value,
+        depth: 0,
+        count: 1,
+    };
+    book.add(&ban, mv("7g7f", 10));
+    book.add(&ban, mv("2g2f", 20));
+    book.add(&ban, mv("7g7f", 30));
+    assert_eq!(book.moves(&ban), &[mv("7g7f", 30), mv("2g2f", 20)]);
+    // an illegal move in the book is never selected
+    book.add(&ban, mv("5e5d", 100));
+    let best = book.probe(&ban, Selection::Best, &mut rand::thread_rng());
+    assert_eq!(best, Some(&mv("7g7f", 30)));
+
+    let error = |text: &str| match Book::parse(text) {
+        Err(Error::Book(message)) => message,
+        e => panic!("{:?}", e),
+    };
+    assert!(error("7g7f none 0 0 1\n").ends_with("(at line 1)"));
+    assert!(error("sfen 9/9 b - 1\n").ends_with("(at line 1)"));
+    assert!(error(&format!("sfen {}\n7g7f none x\n", crate::START_POS)).contains("value"));
+    assert!(error(&format!("sfen {}\n\n7z7f\n", crate::START_POS)).ends_with("(at line 3)"));
+}
+
+#[test]
+fn book_builder() {
+    let record = |moves: &[&str], result| {
+        let mut record = GameRecord::new(Ban::new());
+        for mv in moves {
+            let hand: Hand = (*mv).try_into().unwrap();
+            record.push(crate::record::MoveRecord::new(hand)).unwrap();
+        }
+        record.result = result;
+        record
+    };
+    let mut builder = BookBuilder::new(2);
+    // sente wins twice after 7g7f, and once loses after 2g2f
+    builder.add_record(&record(&["7g7f", "3c3d", "2g2f"], Some(GameResult::Resign)));
+    builder.add_record(&record(&["7g7f", "8c8d", "2g2f"], Some(GameResult::Resign)));
+    builder.add_record(&record(&["2g2f", "8c8d"], Some(GameResult::Resign)));
+    builder.add_record(&record(&["7g7f", "3c3d"], None));
+    assert_eq!(builder.len(), 3);
+
+    let book = builder.build(1, |_, _| None);
+    let ban = Ban::new();
+    let moves = book.moves(&ban);
+    assert_eq!(moves.len(), 2);
+    assert_eq!(String::from(moves[0].hand.clone()), "7g7f");
+    assert_eq!(moves[0].count, 3);
+    assert!(moves[0].value > 0);
+    assert!(moves[1].value < 0);
+    assert_eq!(moves[0].ponder, Some("3c3d".try_into().unwrap()));
+    assert_eq!(moves[0].depth, 0);
+    // the third moves are beyond the ply limit
+    let mut after = ban.clone();
+    after.apply_hand(&"7g7f".try_into().unwrap()).unwrap();
+    after.apply_hand(&"3c3d".try_into().unwrap()).unwrap();
+    assert!(book.moves(&after).is_empty());
+
+    let book = builder.build(2, |_, hand| {
+        Some((
+            if String::from(hand.clone()) == "3c3d" {
+                50
+            } else {
+                0
+            },
+            3,
+        ))
+    });
+    assert_eq!(book.len(), 2);
+    assert_eq!(book.moves(&ban).len(), 1);
+    let mut after = ban.clone();
+    after.apply_hand(&"7g7f".try_into().unwrap()).unwrap();
+    assert_eq!(book.moves(&after)[0].value, 50);
+    assert_eq!(book.moves(&after)[0].depth, 3);
+}
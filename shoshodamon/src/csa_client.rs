@@ -0,0 +1,483 @@
+//! Client of the CSA server protocol used by floodgate and computer shogi tournaments
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::{
+    ban2::Ban2 as Ban,
+    error::Error,
+    kifu::csa,
+    record::{GameRecord, GameResult, MoveRecord},
+    Hand,
+};
+
+/// Time control of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeRule {
+    /// Unit of the times sent by the server
+    pub unit: Duration,
+    pub total: Duration,
+    pub byoyomi: Duration,
+    pub increment: Duration,
+    pub least_per_move: Duration,
+}
+
+/// Game offered by the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSummary {
+    pub game_id: String,
+    pub sente_name: String,
+    pub gote_name: String,
+    /// `true` when we play sente
+    pub my_turn: bool,
+    pub time: TimeRule,
+    pub max_moves: Option<usize>,
+    /// Initial position and the moves already played, e.g. on reconnection
+    pub record: GameRecord,
+}
+
+/// Remaining times of both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// Remaining times of (gote, sente) not including byoyomi
+    pub remaining: [Duration; 2],
+    pub byoyomi: Duration,
+    pub increment: Duration,
+}
+
+impl Clock {
+    fn new(time: &TimeRule) -> Self {
+        Self {
+            remaining: [time.total; 2],
+            byoyomi: time.byoyomi,
+            increment: time.increment,
+        }
+    }
+
+    /// Consume the time of a move by given turn
+    fn consume(&mut self, turn: bool, time: Duration) {
+        let remaining = &mut self.remaining[turn as usize];
+        *remaining = remaining.saturating_sub(time) + self.increment;
+    }
+}
+
+/// What to do in our turn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Move(Hand),
+    Resign,
+    /// Declare win by entering king
+    Declare,
+}
+
+/// Outcome of a game sent by the server, seen from us
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Lose,
+    Draw,
+    /// The game ended without result, e.g. by the move limit
+    Censored,
+    Interrupted,
+}
+
+/// A finished game
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameOver {
+    pub outcome: Outcome,
+    pub record: GameRecord,
+}
+
+pub struct CsaClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl CsaClient {
+    /// Connect to a CSA server
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let writer = TcpStream::connect(addr)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), Error> {
+        self.writer.write_all(format!("{}\n", line).as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Receive a line, skipping empty lines sent to keep alive
+    fn receive(&mut self) -> Result<String, Error> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(Error::Csa("connection closed".to_string()));
+            }
+            let line = line.trim_end();
+            if !line.is_empty() {
+                return Ok(line.to_string());
+            }
+        }
+    }
+
+    pub fn login(&mut self, name: &str, password: &str) -> Result<(), Error> {
+        self.send(&format!("LOGIN {} {}", name, password))?;
+        let line = self.receive()?;
+        if line == format!("LOGIN:{} OK", name) {
+            Ok(())
+        } else {
+            Err(Error::Csa(format!("login failed: {}", line)))
+        }
+    }
+
+    pub fn logout(&mut self) -> Result<(), Error> {
+        self.send("LOGOUT")?;
+        match self.receive() {
+            Ok(line) if line != "LOGOUT:completed" => {
+                Err(Error::Csa(format!("unexpected message {}", line)))
+            }
+            // the server may close the connection without answering
+            _ => Ok(()),
+        }
+    }
+
+    /// Wait for the next game offered by the server
+    pub fn wait_game(&mut self) -> Result<GameSummary, Error> {
+        while self.receive()? != "BEGIN Game_Summary" {}
+        let mut lines = Vec::new();
+        loop {
+            let line = self.receive()?;
+            if line == "END Game_Summary" {
+                break;
+            }
+            lines.push(line);
+        }
+        parse_summary(&lines)
+    }
+
+    /// Agree to play given game. Returns `false` when the opponent rejected it
+    pub fn agree(&mut self, summary: &GameSummary) -> Result<bool, Error> {
+        self.send(&format!("AGREE {}", summary.game_id))?;
+        let line = self.receive()?;
+        if line.starts_with("START:") {
+            Ok(true)
+        } else if line.starts_with("REJECT:") {
+            Ok(false)
+        } else {
+            Err(Error::Csa(format!("unexpected message {}", line)))
+        }
+    }
+
+    /// Play given game until its end, calling `think` in our turns
+    pub fn play<F>(&mut self, summary: &GameSummary, mut think: F) -> Result<GameOver, Error>
+    where
+        F: FnMut(&Ban, &Clock) -> Action,
+    {
+        let mut record = summary.record.clone();
+        let mut ban = record.last_ban()?;
+        let mut clock = Clock::new(&summary.time);
+        let mut turn = record.initial.turn;
+        for mv in &record.moves {
+            clock.consume(turn, mv.time.unwrap_or_default());
+            turn = !turn;
+        }
+
+        let mut thinking = true;
+        let mut reason: Option<String> = None;
+        loop {
+            if ban.turn == summary.my_turn && thinking {
+                let message = match think(&ban, &clock) {
+                    Action::Move(hand) => csa::format_move(&ban, &hand),
+                    Action::Resign => "%TORYO".to_string(),
+                    Action::Declare => "%KACHI".to_string(),
+                };
+                self.send(&message)?;
+                thinking = false;
+            }
+
+            let line = self.receive()?;
+            if line.starts_with('+') || line.starts_with('-') {
+                let mut statements = line.split(',');
+                let hand = csa::parse_move(&ban, statements.next().unwrap_or_default())
+                    .map_err(Error::Csa)?;
+                let time = statements
+                    .find_map(|s| s.strip_prefix('T'))
+                    .and_then(|t| t.parse::<u32>().ok())
+                    .map(|t| summary.time.unit * t);
+                ban.apply_hand(&hand)?;
+                clock.consume(!ban.turn, time.unwrap_or_default());
+                let mut mv = MoveRecord::new(hand);
+                mv.time = time;
+                record.moves.push(mv);
+                thinking = true;
+            } else if line.starts_with('%') {
+                // the echo of resignation or declaration
+            } else if let Some(outcome) = parse_outcome(&line) {
+                record.result = reason
+                    .as_deref()
+                    .and_then(|r| game_result(r, outcome, summary, &ban));
+                return Ok(GameOver { outcome, record });
+            } else if line.starts_with('#') {
+                reason = Some(line);
+            } else {
+                return Err(Error::Csa(format!("unexpected message {}", line)));
+            }
+        }
+    }
+}
+
+fn parse_outcome(line: &str) -> Option<Outcome> {
+    match line {
+        "#WIN" => Some(Outcome::Win),
+        "#LOSE" => Some(Outcome::Lose),
+        "#DRAW" => Some(Outcome::Draw),
+        "#CENSORED" => Some(Outcome::Censored),
+        "#CHUDAN" => Some(Outcome::Interrupted),
+        _ => None,
+    }
+}
+
+/// Get the result seen from the turn after the last move
+fn game_result(
+    reason: &str,
+    outcome: Outcome,
+    summary: &GameSummary,
+    ban: &Ban,
+) -> Option<GameResult> {
+    // whether the side to move lost, for the reasons which do not tell it
+    let turn_lost = match outcome {
+        Outcome::Win => Some(summary.my_turn != ban.turn),
+        Outcome::Lose => Some(summary.my_turn == ban.turn),
+        _ => None,
+    };
+    let result = match (reason, turn_lost) {
+        ("#RESIGN", _) => GameResult::Resign,
+        ("#TIME_UP", _) => GameResult::Timeout,
+        ("#SENNICHITE", _) => GameResult::Sennichite,
+        ("#MAX_MOVES", _) | ("#JISHOGI", None) => GameResult::Jishogi,
+        ("#JISHOGI", Some(false)) => GameResult::Declaration,
+        ("#JISHOGI", Some(true)) | ("#ILLEGAL_MOVE", Some(true)) => GameResult::IllegalMove,
+        ("#OUTE_SENNICHITE", Some(true)) => GameResult::IllegalMove,
+        ("#ILLEGAL_MOVE", Some(false)) | ("#OUTE_SENNICHITE", Some(false)) => {
+            GameResult::IllegalWin
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Parse `Time_Unit` like `1sec`, `1min` or `100msec`
+fn parse_time_unit(s: &str) -> Option<Duration> {
+    let digits = s.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = s.split_at(digits);
+    let count = if count.is_empty() {
+        1
+    } else {
+        count.parse::<u64>().ok()?
+    };
+    match unit {
+        "sec" => Some(Duration::from_secs(count)),
+        "min" => Some(Duration::from_secs(count * 60)),
+        "msec" => Some(Duration::from_millis(count)),
+        _ => None,
+    }
+}
+
+/// Parse the lines between `BEGIN Game_Summary` and `END Game_Summary`
+fn parse_summary(lines: &[String]) -> Result<GameSummary, Error> {
+    let invalid = |line: &str| Error::Csa(format!("invalid summary {}", line));
+    let mut values = Vec::new();
+    let mut position = Vec::new();
+    let mut in_position = false;
+    for line in lines {
+        match line.as_str() {
+            "BEGIN Position" => in_position = true,
+            "END Position" => in_position = false,
+            _ if in_position => position.push(line.as_str()),
+            _ if line.starts_with("BEGIN ") || line.starts_with("END ") => {}
+            _ => {
+                let (key, value) = line.split_once(':').ok_or_else(|| invalid(line))?;
+                values.push((key, value));
+            }
+        }
+    }
+    let value = |key: &str| values.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    let required = |key: &str| value(key).ok_or_else(|| Error::Csa(format!("no {}", key)));
+
+    let unit = match value("Time_Unit") {
+        Some(unit) => parse_time_unit(unit).ok_or_else(|| invalid(unit))?,
+        None => Duration::from_secs(1),
+    };
+    let time = |key: &str| -> Result<Duration, Error> {
+        match value(key) {
+            Some(v) => Ok(unit * v.parse::<u32>().map_err(|_| invalid(v))?),
+            None => Ok(Duration::default()),
+        }
+    };
+    let time = TimeRule {
+        unit,
+        total: time("Total_Time")?,
+        byoyomi: time("Byoyomi")?,
+        increment: time("Increment")?,
+        least_per_move: time("Least_Time_Per_Move")?,
+    };
+
+    let mut record = csa::parse(&position.join("\n"))?;
+    for mv in record.moves.iter_mut() {
+        mv.time = mv.time.map(|t| unit.mul_f64(t.as_secs_f64()));
+    }
+    let (sente_name, gote_name) = (required("Name+")?, required("Name-")?);
+    record
+        .headers
+        .push(("先手".to_string(), sente_name.to_string()));
+    record
+        .headers
+        .push(("後手".to_string(), gote_name.to_string()));
+
+    Ok(GameSummary {
+        game_id: required("Game_ID")?.to_string(),
+        sente_name: sente_name.to_string(),
+        gote_name: gote_name.to_string(),
+        my_turn: match required("Your_Turn")? {
+            "+" => true,
+            "-" => false,
+            turn => return Err(invalid(turn)),
+        },
+        time,
+        max_moves: match value("Max_Moves") {
+            Some(v) => Some(v.parse().map_err(|_| invalid(v))?),
+            None => None,
+        },
+        record,
+    })
+}
+
+#[cfg(test)]
+const SUMMARY: &str = "BEGIN Game_Summary
+Protocol_Version:1.2
+Protocol_Mode:Server
+Format:Shogi 1.0
+Declaration:Jishogi 1.1
+Game_ID:20200101-test-1
+Name+:alice
+Name-:bob
+Your_Turn:-
+Rematch_On_Draw:NO
+To_Move:+
+Max_Moves:256
+BEGIN Time
+Time_Unit:1sec
+Total_Time:600
+Byoyomi:10
+Least_Time_Per_Move:1
+END Time
+BEGIN Position
+PI
++
++7776FU,T12
+END Position
+END Game_Summary
+";
+
+#[test]
+fn summary() {
+    let lines = SUMMARY.lines().map(String::from).collect::<Vec<_>>();
+    let summary = parse_summary(&lines[1..lines.len() - 1]).unwrap();
+    assert_eq!(summary.game_id, "20200101-test-1");
+    assert_eq!(summary.sente_name, "alice");
+    assert!(!summary.my_turn);
+    assert_eq!(summary.max_moves, Some(256));
+    assert_eq!(summary.time.total, Duration::from_secs(600));
+    assert_eq!(summary.time.byoyomi, Duration::from_secs(10));
+    assert_eq!(summary.record.moves.len(), 1);
+    assert_eq!(summary.record.moves[0].time, Some(Duration::from_secs(12)));
+    assert_eq!(summary.record.header("後手"), Some("bob"));
+
+    assert_eq!(parse_time_unit("100msec"), Some(Duration::from_millis(100)));
+    assert_eq!(parse_time_unit("min"), Some(Duration::from_secs(60)));
+    assert_eq!(parse_time_unit("1hour"), None);
+    assert!(parse_summary(&["Game_ID:1".to_string()]).is_err());
+}
+
+#[test]
+fn play_with_mock_server() {
+    use std::{convert::TryInto, net::TcpListener, thread};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut expect = |expected: &str| {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line.trim_end(), expected);
+        };
+        let mut send = |lines: &str| writer.write_all(lines.as_bytes()).unwrap();
+
+        expect("LOGIN bob secret");
+        send("LOGIN:bob OK\n");
+        send(SUMMARY);
+        expect("AGREE 20200101-test-1");
+        send("START:20200101-test-1\n");
+        expect("-3334FU");
+        send("-3334FU,T3\n\n+8822UM,T5\n");
+        expect("%TORYO");
+        send("%TORYO,T1\n#RESIGN\n#LOSE\n");
+        expect("LOGOUT");
+        send("LOGOUT:completed\n");
+    });
+
+    let mut client = CsaClient::connect(addr).unwrap();
+    client.login("bob", "secret").unwrap();
+    let summary = client.wait_game().unwrap();
+    assert!(client.agree(&summary).unwrap());
+
+    let mut clocks = Vec::new();
+    let game = client
+        .play(&summary, |ban, clock| {
+            clocks.push(*clock);
+            if ban.ply == 2 {
+                Action::Move("3c3d".try_into().unwrap())
+            } else {
+                Action::Resign
+            }
+        })
+        .unwrap();
+    client.logout().unwrap();
+    server.join().unwrap();
+
+    assert_eq!(game.outcome, Outcome::Lose);
+    assert_eq!(game.record.result, Some(GameResult::Resign));
+    assert_eq!(game.record.moves.len(), 3);
+    assert_eq!(game.record.moves[1].time, Some(Duration::from_secs(3)));
+    assert_eq!(
+        clocks[1].remaining,
+        [Duration::from_secs(597), Duration::from_secs(583)]
+    );
+}
+
+#[test]
+fn login_failure() {
+    use std::{net::TcpListener, thread};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream.try_clone().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        stream.write_all(b"LOGIN:incorrect\n").unwrap();
+    });
+    let mut client = CsaClient::connect(addr).unwrap();
+    assert!(matches!(client.login("bob", "wrong"), Err(Error::Csa(_))));
+    server.join().unwrap();
+    assert!(matches!(client.wait_game(), Err(Error::Csa(_))));
+}
@@ -0,0 +1,212 @@
+//! Statistics of matches between two engines, i.e. Elo difference and SPRT
+
+/// Results of a match seen from the first engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchScore {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl MatchScore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a game, `Some(true)` for a win of the first engine and `None` for a draw
+    pub fn add(&mut self, win: Option<bool>) {
+        match win {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Mean points per game, counting a draw as half a point
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Elo difference with the half width of its 95% confidence interval. `None` until both
+    /// of a non-win and a non-loss are played, as the difference is infinite
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let n = self.games() as f64;
+        if self.wins + self.draws == 0 || self.losses + self.draws == 0 {
+            return None;
+        }
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.losses as f64 * score.powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2))
+            / n;
+        let margin = 1.959964 * (variance / n).sqrt();
+        let lower = score_to_elo((score - margin).max(1e-6));
+        let upper = score_to_elo((score + margin).min(1.0 - 1e-6));
+        Some((score_to_elo(score), (upper - lower) / 2.0))
+    }
+}
+
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Sequential probability ratio test of `elo0` (H0) against `elo1` (H1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability to accept H1 when H0 is true
+    pub alpha: f64,
+    /// Probability to accept H0 when H1 is true
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    /// Lower and upper bounds of the log likelihood ratio to stop
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log likelihood ratio approximated with the normal distribution of the score, 0 while
+    /// every game has the same result since the variance is unknown then
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let n = score.games() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let (w, d) = (score.wins as f64 / n, score.draws as f64 / n);
+        let mean = w + d / 2.0;
+        let variance = w + d / 4.0 - mean * mean;
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let s0 = elo_to_score(self.elo0);
+        let s1 = elo_to_score(self.elo1);
+        (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance / n)
+    }
+
+    pub fn test(&self, score: &MatchScore) -> SprtResult {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+#[test]
+fn elo() {
+    assert!((score_to_elo(0.5)).abs() < 1e-9);
+    assert!((score_to_elo(0.75) - 190.848).abs() < 1e-3);
+    assert!((elo_to_score(190.848) - 0.75).abs() < 1e-6);
+
+    let mut score = MatchScore::new();
+    assert_eq!(score.elo(), None);
+    score.add(Some(true));
+    assert_eq!(score.elo(), None);
+    score.add(None);
+    score.add(Some(false));
+    score.add(Some(true));
+    assert_eq!(score.games(), 4);
+    assert!((score.score() - 0.625).abs() < 1e-9);
+
+    let score = MatchScore {
+        wins: 60,
+        losses: 40,
+        draws: 0,
+    };
+    let (elo, error) = score.elo().unwrap();
+    assert!((elo - 70.437).abs() < 1e-3);
+    // the score is 0.6 ± 0.096
+    assert!((error - 70.9).abs() < 0.5, "{}", error);
+}
+
+#[test]
+fn sprt() {
+    let sprt = Sprt::new(0.0, 10.0);
+    let (lower, upper) = sprt.bounds();
+    assert!((lower + 2.944).abs() < 1e-3);
+    assert!((upper - 2.944).abs() < 1e-3);
+
+    let even = MatchScore {
+        wins: 100,
+        losses: 100,
+        draws: 100,
+    };
+    assert!(sprt.llr(&even) < 0.0);
+    assert_eq!(sprt.test(&even), SprtResult::Continue);
+    assert_eq!(
+        sprt.test(&MatchScore {
+            wins: 3000,
+            losses: 3000,
+            draws: 3000
+        }),
+        SprtResult::AcceptH0
+    );
+    assert_eq!(
+        sprt.test(&MatchScore {
+            wins: 600,
+            losses: 400,
+            draws: 200
+        }),
+        SprtResult::AcceptH1
+    );
+    // shogi games are rarely drawn
+    assert_eq!(
+        sprt.test(&MatchScore {
+            wins: 600,
+            losses: 400,
+            draws: 0
+        }),
+        SprtResult::AcceptH1
+    );
+    assert!(
+        sprt.llr(&MatchScore {
+            wins: 5,
+            losses: 0,
+            draws: 3
+        }) > 0.0
+    );
+    for (wins, losses, draws) in [(0, 0, 0), (5, 0, 0), (0, 5, 0), (0, 0, 5)].iter() {
+        let score = MatchScore {
+            wins: *wins,
+            losses: *losses,
+            draws: *draws,
+        };
+        assert_eq!(sprt.llr(&score), 0.0);
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use crate::Piece;
+
+/// Errors returned from parsing and moving pieces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Given sfen is malformed
+    Sfen(SfenError),
+    /// Given move is not allowed in current position
+    IllegalMove(IllegalMoveKind),
+    /// Given character is not a piece
+    InvalidPiece(char),
+    /// Given string is not a usi move
+    InvalidHand(String),
+    /// Given string is not a move in the notation
+    Notation(String),
+    /// Given position breaks the rules
+    InvalidPosition(Vec<Violation>),
+    /// Given kifu is malformed
+    Kifu(KifuError),
+    /// Reading or writing a connection failed
+    Io(String),
+    /// The server sent an unexpected message
+    Csa(String),
+    /// Given opening book is malformed
+    Book(String),
+    /// The engine sent an unexpected message or did not answer in time
+    Usi(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Sfen(e) => write!(f, "{}", e),
+            Error::IllegalMove(kind) => write!(f, "Illegal move: {}", kind),
+            Error::InvalidPiece(c) => write!(f, "Invalid piece: {}", c),
+            Error::InvalidHand(s) => write!(f, "Invalid move: {}", s),
+            Error::Notation(s) => write!(f, "Invalid notation: {}", s),
+            Error::InvalidPosition(violations) => {
+                write!(f, "Invalid position: ")?;
+                for (i, v) in violations.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                Ok(())
+            }
+            Error::Kifu(e) => write!(f, "{}", e),
+            Error::Io(message) => write!(f, "IO error: {}", message),
+            Error::Csa(message) => write!(f, "CSA protocol error: {}", message),
+            Error::Book(message) => write!(f, "Invalid book: {}", message),
+            Error::Usi(message) => write!(f, "USI protocol error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+impl From<SfenError> for Error {
+    fn from(e: SfenError) -> Self {
+        Error::Sfen(e)
+    }
+}
+
+impl From<IllegalMoveKind> for Error {
+    fn from(kind: IllegalMoveKind) -> Self {
+        Error::IllegalMove(kind)
+    }
+}
+
+/// Fields of sfen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfenField {
+    Board,
+    Turn,
+    Havings,
+    MoveCount,
+}
+
+impl fmt::Display for SfenField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SfenField::Board => "board",
+            SfenField::Turn => "turn",
+            SfenField::Havings => "havings",
+            SfenField::MoveCount => "move count",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Located error of sfen. `position` is the character index in the whole sfen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SfenError {
+    pub field: SfenField,
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for SfenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot parse sfen - {} (near {} at {})",
+            self.message, self.field, self.position
+        )
+    }
+}
+
+impl std::error::Error for SfenError {}
+
+/// Error of kifu with its 1-indexed line, which is 0 for formats without lines like JKF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KifuError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for KifuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "Cannot parse kifu - {}", self.message);
+        }
+        write!(
+            f,
+            "Cannot parse kifu - {} (at line {})",
+            self.message, self.line
+        )
+    }
+}
+
+impl std::error::Error for KifuError {}
+
+/// Reasons why a move is illegal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMoveKind {
+    /// Given position is out of the board
+    OutOfBoard,
+    /// No piece exists at the moving position
+    NoPiece,
+    /// The moving piece is the opponent's
+    NotYourPiece,
+    /// The piece cannot reach the destination
+    CannotReach,
+    /// The destination is occupied by your piece
+    OccupiedByYourPiece,
+    /// The putting position is occupied
+    Occupied,
+    /// The piece does not exist in your havings
+    OutOfHand,
+    /// Neither the moving nor the destination position is in the promotion zone
+    OutOfPromotionZone,
+    /// The piece is already promoted
+    AlreadyPromoted,
+    /// Kin and Ou cannot promote
+    Unpromotable,
+    /// The piece would have no next move without promotion
+    NoNextMove,
+    /// Two unpromoted Fu on the same file
+    Nifu,
+    /// Checkmate by putting Fu
+    Uchifuzume,
+    /// Ou cannot be put
+    PutOu,
+}
+
+impl fmt::Display for IllegalMoveKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            IllegalMoveKind::OutOfBoard => "position is out of the board",
+            IllegalMoveKind::NoPiece => "no piece at given position",
+            IllegalMoveKind::NotYourPiece => "specified position is not your piece",
+            IllegalMoveKind::CannotReach => "cannot move piece to specified position",
+            IllegalMoveKind::OccupiedByYourPiece => "cannot move piece to your piece",
+            IllegalMoveKind::Occupied => "a piece already exists at specified position",
+            IllegalMoveKind::OutOfHand => "cannot find the piece in your havings",
+            IllegalMoveKind::OutOfPromotionZone => "cannot promote at specified position",
+            IllegalMoveKind::AlreadyPromoted => "piece is already promoted",
+            IllegalMoveKind::Unpromotable => "Kin and Ou cannot promote",
+            IllegalMoveKind::NoNextMove => "piece has no next move without promotion",
+            IllegalMoveKind::Nifu => "nifu",
+            IllegalMoveKind::Uchifuzume => "uchifuzume",
+            IllegalMoveKind::PutOu => "Ou cannot be put",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Invariants of a legal position broken by `Ban2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The turn has no Ou
+    NoOu { turn: bool },
+    /// The turn has more than one Ou
+    TooManyOu { turn: bool, count: usize },
+    /// Two unpromoted Fu of the turn on the file `x`
+    Nifu { turn: bool, x: usize },
+    /// Unpromoted Fu, Kyosha or Keima at (x, y) has no next move
+    NoNextMove { x: usize, y: usize },
+    /// More pieces than a game has, counting both board and havings
+    TooManyPieces { piece: Piece, count: usize },
+    /// The side not to move is checked
+    OpponentChecked,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |turn: &bool| if *turn { "sente" } else { "gote" };
+        match self {
+            Violation::NoOu { turn } => write!(f, "{} has no Ou", side(turn)),
+            Violation::TooManyOu { turn, count } => write!(f, "{} has {} Ou", side(turn), count),
+            Violation::Nifu { turn, x } => write!(f, "nifu of {} on file {}", side(turn), x),
+            Violation::NoNextMove { x, y } => write!(f, "piece at {}{} has no next move", x, y),
+            Violation::TooManyPieces { piece, count } => {
+                write!(f, "too many {:?} ({})", piece, count)
+            }
+            Violation::OpponentChecked => write!(f, "the side not to move is checked"),
+        }
+    }
+}
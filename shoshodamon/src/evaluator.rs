@@ -1,16 +1,16 @@
 use std::{
     collections::LinkedList,
     sync::{
-        atomic::{AtomicI32, Ordering},
+        atomic::{AtomicBool, AtomicI32, Ordering},
         Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use once_cell::sync::Lazy;
 
-use crate::{ban2::Ban2 as Ban, Hand, Piece};
+use crate::{ban2::Ban2 as Ban, mate::mate_in_one, Hand, Piece};
 
 #[derive(Clone)]
 struct BanBeforeHands(Ban, Vec<Hand>);
@@ -136,6 +136,25 @@ fn get_evaluated_value(ban: &Ban) -> i32 {
 }
 
 pub static COUNT: Lazy<AtomicI32> = Lazy::new(|| AtomicI32::new(0));
+/// Set to stop `alpha_beta2` and `mate::solve_mate` on the way, whose results are incomplete
+/// then
+pub static STOP: AtomicBool = AtomicBool::new(false);
+/// `alpha_beta2` and `mate::solve_mate` set `STOP` when they are still searching at this time
+pub static DEADLINE: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+/// Nodes between checks of `DEADLINE`
+const DEADLINE_INTERVAL: i32 = 256;
+
+/// Check `STOP` at the `count`th node of a search, reading `DEADLINE` at some of the nodes
+pub(crate) fn should_stop(count: i32) -> bool {
+    if count % DEADLINE_INTERVAL == 0 {
+        if let Some(deadline) = *DEADLINE.lock().unwrap() {
+            if Instant::now() >= deadline {
+                STOP.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+    STOP.load(Ordering::Relaxed)
+}
 
 #[allow(clippy::collapsible_else_if)]
 pub fn alpha_beta2(
@@ -146,10 +165,17 @@ pub fn alpha_beta2(
     max_depth: usize,
     is_mine: bool,
 ) -> Option<(Vec<Hand>, isize)> {
-    COUNT.fetch_add(1, Ordering::Release);
+    if should_stop(COUNT.fetch_add(1, Ordering::Release)) {
+        return None;
+    }
     if ban.is_check_mate(ban.turn) {
         Some((hands, if ban.turn { -29999 } else { 29999 }))
     } else if hands.len() == max_depth {
+        if let Some(hand) = mate_in_one(ban) {
+            let mut hands = hands;
+            hands.push(hand);
+            return Some((hands, if ban.turn { 29999 } else { -29999 }));
+        }
         Some((
             hands,
             (get_evaluated_value(ban) as isize), // * if ban.turn { 1 } else { -1 },
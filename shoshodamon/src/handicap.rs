@@ -0,0 +1,207 @@
+//! Handicap (komaochi) presets. Except `Hirate`, gote (uwate) removes pieces and moves first
+
+use crate::ban2::Ban2 as Ban;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Handicap {
+    Hirate,
+    Lance,
+    RightLance,
+    Bishop,
+    Rook,
+    RookLance,
+    TwoPieces,
+    ThreePieces,
+    FourPieces,
+    FivePieces,
+    LeftFivePieces,
+    SixPieces,
+    LeftSevenPieces,
+    RightSevenPieces,
+    EightPieces,
+    TenPieces,
+}
+
+/// Presets with their `手合割` names, identifiers and initial positions
+const PRESETS: [(Handicap, &str, &str, &str); 16] = [
+    (
+        Handicap::Hirate,
+        "平手",
+        "even",
+        "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+    ),
+    (
+        Handicap::Lance,
+        "香落ち",
+        "lance",
+        "lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::RightLance,
+        "右香落ち",
+        "right-lance",
+        "1nsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::Bishop,
+        "角落ち",
+        "bishop",
+        "lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::Rook,
+        "飛車落ち",
+        "rook",
+        "lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::RookLance,
+        "飛香落ち",
+        "rook-lance",
+        "lnsgkgsn1/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::TwoPieces,
+        "二枚落ち",
+        "2-piece",
+        "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::ThreePieces,
+        "三枚落ち",
+        "3-piece",
+        "lnsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::FourPieces,
+        "四枚落ち",
+        "4-piece",
+        "1nsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::FivePieces,
+        "五枚落ち",
+        "5-piece",
+        "2sgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::LeftFivePieces,
+        "左五枚落ち",
+        "left-5-piece",
+        "1nsgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::SixPieces,
+        "六枚落ち",
+        "6-piece",
+        "2sgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::LeftSevenPieces,
+        "左七枚落ち",
+        "left-7-piece",
+        "2sgkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::RightSevenPieces,
+        "右七枚落ち",
+        "right-7-piece",
+        "3gkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::EightPieces,
+        "八枚落ち",
+        "8-piece",
+        "3gkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+    (
+        Handicap::TenPieces,
+        "十枚落ち",
+        "10-piece",
+        "4k4/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ),
+];
+
+/// Other names of `手合割` accepted by readers
+const ALIASES: [(&str, Handicap); 1] = [("飛落ち", Handicap::Rook)];
+
+impl Handicap {
+    pub const ALL: [Handicap; 16] = [
+        Handicap::Hirate,
+        Handicap::Lance,
+        Handicap::RightLance,
+        Handicap::Bishop,
+        Handicap::Rook,
+        Handicap::RookLance,
+        Handicap::TwoPieces,
+        Handicap::ThreePieces,
+        Handicap::FourPieces,
+        Handicap::FivePieces,
+        Handicap::LeftFivePieces,
+        Handicap::SixPieces,
+        Handicap::LeftSevenPieces,
+        Handicap::RightSevenPieces,
+        Handicap::EightPieces,
+        Handicap::TenPieces,
+    ];
+
+    fn preset(self) -> &'static (Handicap, &'static str, &'static str, &'static str) {
+        PRESETS.iter().find(|(h, _, _, _)| *h == self).unwrap()
+    }
+
+    /// Get the name written in `手合割`, like `香落ち`
+    pub fn name(self) -> &'static str {
+        self.preset().1
+    }
+
+    /// Get the ascii identifier, like `lance` or `2-piece`
+    pub fn id(self) -> &'static str {
+        self.preset().2
+    }
+
+    pub fn sfen(self) -> &'static str {
+        self.preset().3
+    }
+
+    pub fn ban(self) -> Ban {
+        Ban::from_sfen(self.sfen()).unwrap()
+    }
+
+    /// Get the side to move first, which is gote except `Hirate`
+    pub fn turn(self) -> bool {
+        self == Handicap::Hirate
+    }
+
+    /// Find a handicap by its `手合割` name or its identifier
+    pub fn from_name(name: &str) -> Option<Self> {
+        PRESETS
+            .iter()
+            .find(|(_, n, id, _)| *n == name || id.eq_ignore_ascii_case(name))
+            .map(|(h, _, _, _)| *h)
+            .or_else(|| ALIASES.iter().find(|(n, _)| *n == name).map(|(_, h)| *h))
+    }
+
+    /// Find the handicap whose initial position is given one
+    pub fn from_ban(ban: &Ban) -> Option<Self> {
+        let sfen = ban.to_sfen();
+        PRESETS
+            .iter()
+            .find(|(_, _, _, s)| *s == sfen)
+            .map(|(h, _, _, _)| *h)
+    }
+}
+
+#[test]
+fn handicap() {
+    for handicap in Handicap::ALL.iter() {
+        let ban = Ban::from_sfen_validated(handicap.sfen()).unwrap();
+        assert_eq!(ban.turn, handicap.turn());
+        assert_eq!(Handicap::from_ban(&ban), Some(*handicap));
+        assert_eq!(Handicap::from_name(handicap.name()), Some(*handicap));
+        assert_eq!(Handicap::from_name(handicap.id()), Some(*handicap));
+    }
+    assert_eq!(Handicap::Hirate.sfen(), crate::START_POS);
+    assert_eq!(Handicap::from_name("Rook-Lance"), Some(Handicap::RookLance));
+    assert_eq!(Handicap::from_name("飛落ち"), Some(Handicap::Rook));
+    assert_eq!(Handicap::from_name("飛角落ち"), None);
+}
@@ -0,0 +1,405 @@
+//! Readers and writers of kifu formats
+
+pub mod bod;
+pub mod csa;
+#[cfg(feature = "serde")]
+pub mod jkf;
+pub mod ki2;
+pub mod kif;
+
+use std::time::Duration;
+
+use crate::{
+    ban2::Ban2 as Ban,
+    error::{Error, KifuError},
+    handicap::Handicap,
+    notation::japanese,
+    record::{GameRecord, MoveRecord},
+    Hand, Piece, PieceBoolPair,
+};
+
+/// Kifu formats supported by readers and writers. JKF needs the `serde` feature, so matches
+/// outside this crate must not rely on the set of formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KifuFormat {
+    Kif,
+    Ki2,
+    Csa,
+    #[cfg(feature = "serde")]
+    Jkf,
+}
+
+impl KifuFormat {
+    /// Get the format of given file extension like `kif`
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "kif" | "kifu" => Some(KifuFormat::Kif),
+            "ki2" | "ki2u" => Some(KifuFormat::Ki2),
+            "csa" => Some(KifuFormat::Csa),
+            #[cfg(feature = "serde")]
+            "jkf" | "json" => Some(KifuFormat::Jkf),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self, kifu: &str) -> Result<GameRecord, Error> {
+        match self {
+            KifuFormat::Kif => kif::parse(kifu),
+            KifuFormat::Ki2 => ki2::parse(kifu),
+            KifuFormat::Csa => csa::parse(kifu),
+            #[cfg(feature = "serde")]
+            KifuFormat::Jkf => jkf::parse(kifu),
+        }
+    }
+
+    pub fn write(&self, record: &GameRecord) -> String {
+        match self {
+            KifuFormat::Kif => kif::write(record),
+            KifuFormat::Ki2 => ki2::write(record),
+            KifuFormat::Csa => csa::write(record),
+            #[cfg(feature = "serde")]
+            KifuFormat::Jkf => jkf::write(record),
+        }
+    }
+}
+
+/// Make a position via sfen, so that it is validated like the other positions. `board` has
+/// (turn, piece, promoted) indexed like `Ban2` and `havings` has (turn, piece)
+fn build_ban(
+    board: &[Option<(bool, Piece, bool)>; 81],
+    havings: &[(bool, Piece)],
+    turn: bool,
+) -> Result<Ban, String> {
+    let mut sfen = String::new();
+    for y in 1..=9 {
+        let mut none_count = 0;
+        for x in (1..=9).rev() {
+            match board[(x - 1) + (y - 1) * 9] {
+                Some((turn, piece, promoted)) => {
+                    if none_count != 0 {
+                        sfen.push_str(&none_count.to_string());
+                        none_count = 0;
+                    }
+                    if promoted {
+                        sfen.push('+');
+                    }
+                    sfen.push(PieceBoolPair(piece, turn).into());
+                }
+                None => none_count += 1,
+            }
+        }
+        if none_count != 0 {
+            sfen.push_str(&none_count.to_string());
+        }
+        sfen.push(if y == 9 { ' ' } else { '/' });
+    }
+    sfen.push_str(if turn { "b " } else { "w " });
+    if havings.is_empty() {
+        sfen.push('-');
+    }
+    for (turn, piece) in havings {
+        sfen.push(PieceBoolPair(*piece, *turn).into());
+    }
+    sfen.push_str(" 1");
+    Ban::from_sfen(&sfen).map_err(|e| e.to_string())
+}
+
+/// A line of moves in `GameRecord`, as the path of (move index, variation index) from main line
+struct Line {
+    path: Vec<(usize, usize)>,
+    /// 1-indexed ply of the first move
+    start: usize,
+    len: usize,
+}
+
+/// Builds a `GameRecord` move by move, keeping track of variations
+struct RecordBuilder {
+    record: GameRecord,
+    lines: Vec<Line>,
+    current: usize,
+    ban: Ban,
+    last: Option<(usize, usize)>,
+}
+
+impl RecordBuilder {
+    fn new(record: GameRecord) -> Self {
+        let ban = record.initial.clone();
+        // positions after `手数＝N` are numbered from N + 1
+        let start = ban.ply.max(1);
+        Self {
+            record,
+            lines: vec![Line {
+                path: Vec::new(),
+                start,
+                len: 0,
+            }],
+            current: 0,
+            ban,
+            last: None,
+        }
+    }
+
+    fn line_mut<'a>(
+        moves: &'a mut Vec<MoveRecord>,
+        path: &[(usize, usize)],
+    ) -> &'a mut Vec<MoveRecord> {
+        match path.split_first() {
+            None => moves,
+            Some(((i, v), rest)) => Self::line_mut(&mut moves[*i].variations[*v], rest),
+        }
+    }
+
+    fn current_line(&mut self) -> &mut Vec<MoveRecord> {
+        let path = self.lines[self.current].path.clone();
+        Self::line_mut(&mut self.record.moves, &path)
+    }
+
+    /// Apply the move to the position and push it to the current line
+    fn push(&mut self, mv: MoveRecord) -> Result<(), String> {
+        self.ban.apply_hand(&mv.hand).map_err(|e| e.to_string())?;
+        self.last = Some(mv.hand.destination());
+        self.lines[self.current].len += 1;
+        self.current_line().push(mv);
+        Ok(())
+    }
+
+    fn comment(&mut self, comment: String) {
+        let main = self.is_main_line();
+        match self.current_line().last_mut() {
+            Some(mv) => mv.comments.push(comment),
+            None if main => self.record.comments.push(comment),
+            None => {}
+        }
+    }
+
+    /// Start a variation which replaces the move of given ply
+    fn start_variation(&mut self, ply: usize) -> Result<(), String> {
+        let parent = (0..self.lines.len())
+            .rev()
+            .find(|i| {
+                let line = &self.lines[*i];
+                line.start <= ply && ply < line.start + line.len
+            })
+            .ok_or_else(|| format!("no move to branch at {}", ply))?;
+
+        let index = ply - self.lines[parent].start;
+        let mut path = self.lines[parent].path.clone();
+        let parent_path = path.clone();
+        let parent_line = Self::line_mut(&mut self.record.moves, &parent_path);
+        parent_line[index].variations.push(Vec::new());
+        path.push((index, parent_line[index].variations.len() - 1));
+
+        let mut ban = self.record.initial.clone();
+        let mut last = None;
+        for hand in self.hands_before(&path) {
+            ban.apply_hand(&hand).map_err(|e| e.to_string())?;
+            last = Some(hand.destination());
+        }
+        self.ban = ban;
+        self.last = last;
+        self.lines.push(Line {
+            path,
+            start: ply,
+            len: 0,
+        });
+        self.current = self.lines.len() - 1;
+        Ok(())
+    }
+
+    /// Get all moves played before the line of given path
+    fn hands_before(&self, path: &[(usize, usize)]) -> Vec<Hand> {
+        let mut hands = Vec::new();
+        let mut moves = &self.record.moves;
+        for (i, v) in path {
+            hands.extend(moves[..*i].iter().map(|m| m.hand.clone()));
+            moves = &moves[*i].variations[*v];
+        }
+        hands
+    }
+
+    /// Get the 1-indexed ply of the next move in the current line
+    fn next_ply(&self) -> usize {
+        let line = &self.lines[self.current];
+        line.start + line.len
+    }
+
+    fn is_main_line(&self) -> bool {
+        self.current == 0
+    }
+
+    fn finish(self) -> GameRecord {
+        self.record
+    }
+}
+
+/// Make an error of given 0-indexed line
+fn kifu_error(line: usize, message: impl Into<String>) -> Error {
+    Error::Kifu(KifuError {
+        line: line + 1,
+        message: message.into(),
+    })
+}
+
+/// Seconds of a time for formats without fractions, rounded to the nearest
+fn whole_seconds(time: Duration) -> u64 {
+    ((time.as_millis() + 500) / 1000) as u64
+}
+
+/// Split a header line like `先手：name`
+fn split_header(line: &str) -> Option<(&str, &str)> {
+    let mut splited = line.splitn(2, '：');
+    let key = splited.next()?.trim();
+    let value = splited.next()?.trim();
+    if key.is_empty() {
+        None
+    } else {
+        Some((key, value))
+    }
+}
+
+/// `手合割` of positions given by BOD boards
+const OTHER_HANDICAP: &str = "その他";
+
+/// Check the value of a header known by readers
+fn check_header(key: &str, value: &str) -> Result<(), String> {
+    if key == "手合割" && value != OTHER_HANDICAP && Handicap::from_name(value).is_none() {
+        return Err(format!("unknown handicap {}", value));
+    }
+    Ok(())
+}
+
+/// Parse `変化：N手` and get N
+fn parse_variation(line: &str) -> Option<usize> {
+    line.strip_prefix("変化：")?
+        .trim()
+        .strip_suffix('手')?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Make a `GameRecord` with given headers, taking the initial position from the BOD board if
+/// any, or `手合割`. `line` is the 0-indexed line where headers end
+fn record_from_headers(
+    headers: Vec<(String, String)>,
+    board: &[(usize, &str)],
+    line: usize,
+) -> Result<GameRecord, Error> {
+    let initial = if !board.is_empty() {
+        bod::parse_lines(board)?
+    } else {
+        match headers.iter().find(|(k, _)| k == "手合割") {
+            Some((_, name)) if name == OTHER_HANDICAP => {
+                return Err(kifu_error(line, "no board of the initial position"))
+            }
+            Some((_, name)) => Handicap::from_name(name)
+                .ok_or_else(|| kifu_error(line, format!("unknown handicap {}", name)))?
+                .ban(),
+            None => Ban::new(),
+        }
+    };
+    let mut record = GameRecord::new(initial);
+    record.headers = headers;
+    Ok(record)
+}
+
+/// Write headers, adding `手合割` if the initial position is a known handicap, or a BOD board
+/// otherwise or when the game starts after `手数＝N`
+fn write_headers(record: &GameRecord, out: &mut String) {
+    let handicap = Handicap::from_ban(&record.initial);
+    if let (None, Some(handicap)) = (record.header("手合割"), handicap) {
+        out.push_str(&format!("手合割：{}\n", handicap.name()));
+    }
+    for (key, value) in &record.headers {
+        out.push_str(&format!("{}：{}\n", key, value));
+    }
+    if handicap.is_none() || record.initial.ply > 1 {
+        out.push_str(&bod::write(&record.initial));
+    }
+    for comment in &record.comments {
+        out.push_str(&format!("*{}\n", comment));
+    }
+}
+
+/// Write `まで...` line of the result
+fn write_summary(record: &GameRecord, out: &mut String) {
+    let handicap = !matches!(
+        Handicap::from_ban(&record.initial),
+        Some(Handicap::Hirate) | None
+    );
+    let side = |sente: bool| match (sente, handicap) {
+        (true, false) => "先手",
+        (false, false) => "後手",
+        (true, true) => "下手",
+        (false, true) => "上手",
+    };
+    if let Some(result) = record.result {
+        let summary = match record.winner() {
+            Some(winner) => format!("{}の勝ち", side(winner)),
+            None => japanese::result_name(result).to_string(),
+        };
+        let plies = first_ply(record) - 1 + record.moves.len();
+        out.push_str(&format!("まで{}手で{}\n", plies, summary));
+    }
+}
+
+/// Get the number of the first move, which is not 1 for positions after `手数＝N`
+fn first_ply(record: &GameRecord) -> usize {
+    record.initial.ply.max(1)
+}
+
+/// State before the first move of a line, used while writing
+#[derive(Clone)]
+struct LineStart {
+    ban: Ban,
+    last: Option<(usize, usize)>,
+    /// Total times consumed by (gote, sente)
+    times: [Duration; 2],
+}
+
+impl LineStart {
+    fn new(ban: &Ban) -> Self {
+        Self {
+            ban: ban.clone(),
+            last: None,
+            times: [Duration::default(); 2],
+        }
+    }
+
+    /// Get the state after given move. `None` when the move is illegal
+    fn next(&self, mv: &MoveRecord) -> Option<Self> {
+        let mut next = self.clone();
+        next.times[self.ban.turn as usize] += mv.time.unwrap_or_default();
+        next.ban.apply_hand(&mv.hand).ok()?;
+        next.last = Some(mv.hand.destination());
+        Some(next)
+    }
+}
+
+/// Write variations of the line, later ones first as kifu readers attach each variation to the
+/// latest line having the branched move
+fn write_variations<F>(
+    moves: &[MoveRecord],
+    ply: usize,
+    start: &LineStart,
+    out: &mut String,
+    write_line: &mut F,
+) where
+    F: FnMut(&[MoveRecord], usize, &LineStart, &mut String),
+{
+    let mut states = vec![start.clone()];
+    for mv in moves {
+        match states.last().unwrap().next(mv) {
+            Some(next) => states.push(next),
+            None => break,
+        }
+    }
+    for (i, state) in states.iter().enumerate().take(moves.len()).rev() {
+        for variation in &moves[i].variations {
+            out.push_str(&format!("\n変化：{}手\n", ply + i));
+            write_line(variation, ply + i, state, out);
+            write_variations(variation, ply + i, state, out, write_line);
+        }
+    }
+}
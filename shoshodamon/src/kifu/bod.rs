@@ -0,0 +1,230 @@
+//! BOD board diagrams, e.g.
+//! ```text
+//! 後手の持駒：なし
+//!   ９ ８ ７ ６ ５ ４ ３ ２ １
+//! +---------------------------+
+//! |v香v桂v銀v金v玉v金v銀v桂v香|一
+//! ...
+//! +---------------------------+
+//! 先手の持駒：なし
+//! 先手番
+//! ```
+
+use super::{build_ban, japanese, kifu_error};
+use crate::{
+    ban2::{render::Renderer, Ban2 as Ban},
+    error::Error,
+    notation::japanese::KANJI_DIGITS,
+    Piece,
+};
+
+/// Keys of pieces in hand with their turns, `上手` and `下手` are used in handicap games
+const HAVING_KEYS: [(&str, bool); 4] = [
+    ("先手の持駒", true),
+    ("下手の持駒", true),
+    ("後手の持駒", false),
+    ("上手の持駒", false),
+];
+
+const TURN_LINES: [(&str, bool); 4] = [
+    ("先手番", true),
+    ("下手番", true),
+    ("後手番", false),
+    ("上手番", false),
+];
+
+/// Check given line is a part of a BOD board, so that kifu readers can collect them
+pub(crate) fn is_bod_line(line: &str) -> bool {
+    let line = line.trim_end();
+    line.starts_with('|')
+        || line.starts_with("+--")
+        || line.starts_with("手数＝")
+        || line.trim_start().starts_with('９')
+        || TURN_LINES.iter().any(|(l, _)| line == *l)
+        || line
+            .split_once('：')
+            .is_some_and(|(key, _)| HAVING_KEYS.iter().any(|(k, _)| key == *k))
+}
+
+/// Parse a BOD board, ignoring lines around it
+pub fn parse(bod: &str) -> Result<Ban, Error> {
+    let lines = bod
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}'))
+        .enumerate()
+        .filter(|(_, line)| is_bod_line(line))
+        .collect::<Vec<_>>();
+    parse_lines(&lines)
+}
+
+/// Parse BOD lines with their 0-indexed line numbers
+pub(crate) fn parse_lines(lines: &[(usize, &str)]) -> Result<Ban, Error> {
+    let mut board = [None; 81];
+    let mut havings = Vec::new();
+    let mut turn = true;
+    let mut ply = 1;
+    let mut ranks = 0;
+
+    for &(i, line) in lines {
+        let line = line.trim_end();
+        if let Some(row) = line.strip_prefix('|') {
+            if ranks == 9 {
+                return Err(kifu_error(i, "too many ranks"));
+            }
+            ranks += 1;
+            parse_rank(row, ranks, &mut board).map_err(|e| kifu_error(i, e))?;
+        } else if let Some(count) = line.strip_prefix("手数＝") {
+            ply = count
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse::<usize>().ok())
+                .ok_or_else(|| kifu_error(i, format!("invalid move count {}", count)))?
+                + 1;
+        } else if let Some((_, t)) = TURN_LINES.iter().find(|(l, _)| line == *l) {
+            turn = *t;
+        } else if let Some((key, value)) = line.split_once('：') {
+            if let Some((_, t)) = HAVING_KEYS.iter().find(|(k, _)| key == *k) {
+                for (piece, count) in parse_havings(value).map_err(|e| kifu_error(i, e))? {
+                    (0..count).for_each(|_| havings.push((*t, piece)));
+                }
+            }
+        }
+    }
+
+    let last = lines.last().map_or(0, |(i, _)| *i);
+    if ranks != 9 {
+        return Err(kifu_error(
+            last,
+            format!("expected 9 ranks but got {}", ranks),
+        ));
+    }
+    let mut ban = build_ban(&board, &havings, turn).map_err(|e| kifu_error(last, e))?;
+    ban.ply = ply;
+    Ok(ban)
+}
+
+/// Parse a rank like `v香v桂 ・ ・v玉 ・ ・ 桂 香|一`, a cell is a `v` for gote or a space,
+/// and a piece or `・`
+fn parse_rank(
+    row: &str,
+    y: usize,
+    board: &mut [Option<(bool, Piece, bool)>; 81],
+) -> Result<(), String> {
+    let chars = row.chars().collect::<Vec<_>>();
+    for x in (1..=9).rev() {
+        let i = (9 - x) * 2;
+        let (mark, name) = match (chars.get(i), chars.get(i + 1)) {
+            (Some(mark), Some(name)) => (*mark, *name),
+            _ => return Err(format!("rank must have 9 squares: {}", row)),
+        };
+        if name == '・' {
+            continue;
+        }
+        let turn = match mark {
+            'v' | 'V' => false,
+            ' ' | '^' => true,
+            _ => return Err(format!("invalid mark {}", mark)),
+        };
+        let (piece, promoted, _) = japanese::parse_piece_name(&name.to_string())
+            .ok_or_else(|| format!("invalid piece {}", name))?;
+        board[(x - 1) + (y - 1) * 9] = Some((turn, piece, promoted));
+    }
+    Ok(())
+}
+
+/// Parse pieces in hand like `飛　歩十二　` or `なし`
+fn parse_havings(s: &str) -> Result<Vec<(Piece, usize)>, String> {
+    let s = s.trim();
+    if s == "なし" {
+        return Ok(Vec::new());
+    }
+    s.split(['　', ' '])
+        .filter(|item| !item.is_empty())
+        .map(|item| match japanese::parse_piece_name(item) {
+            Some((piece, false, count)) if piece != Piece::Ou => parse_kanji_number(count)
+                .map(|count| (piece, count))
+                .ok_or_else(|| format!("invalid count of {}", item)),
+            _ => Err(format!("invalid piece in hand {}", item)),
+        })
+        .collect()
+}
+
+/// Parse a count like `三` or `十八`, which is 1 when omitted
+fn parse_kanji_number(s: &str) -> Option<usize> {
+    let digit = |s: &str| {
+        let mut chars = s.chars();
+        let c = chars.next()?;
+        if chars.next().is_some() {
+            return None;
+        }
+        KANJI_DIGITS.iter().position(|d| *d == c).map(|n| n + 1)
+    };
+    match s.strip_prefix('十') {
+        Some("") => Some(10),
+        Some(rest) => digit(rest).map(|n| n + 10),
+        None if s.is_empty() => Some(1),
+        None => digit(s),
+    }
+}
+
+/// Write given position as a BOD board
+pub fn write(ban: &Ban) -> String {
+    Renderer::kanji().render(ban)
+}
+
+#[test]
+fn bod_round_trip() {
+    let sfens = [
+        crate::START_POS,
+        "lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+        "4k4/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+        "7nl/6gk1/5Spp1/8p/9/9/9/9/K8 b RBG2s2n3l14p 1",
+        "ln1g3nl/1r3kg2/p2pppsp1/2p3p1p/1p5P1/2P1P1P2/PPBP1P2P/2S2S1R1/LN1GKG1NL w Bs 24",
+        "l2+R4l/4kg3/2n1pp1+P1/p1p2b2p/1N1p5/P1P1P3P/1PS2P1P1/1KG1G4/LN6L b RS2Pbgsp 77",
+        "8k/9/9/9/9/9/9/9/K8 b 2r2b4g4s4n4l18p 1",
+        "+l+n+s1k1+s+n+l/1+r5+b1/+p+p+p+p+p+p+p+p+p/9/9/9/9/9/4K4 w 2G2g 3",
+    ];
+    for sfen in sfens.iter() {
+        let ban = Ban::from_sfen(sfen).unwrap();
+        let bod = write(&ban);
+        let parsed = parse(&bod).unwrap();
+        assert_eq!(parsed, ban, "{}", bod);
+        assert_eq!(parsed.to_sfen(), *sfen);
+    }
+}
+
+#[test]
+fn bod_parse() {
+    let bod = "第1問 3手詰
+上手の持駒：飛二　角　金三　銀三　桂三　香三　歩十六
+  ９ ８ ７ ６ ５ ４ ３ ２ １
++---------------------------+
+| ・ ・ ・ ・ ・ ・ ・v桂v香|一
+| ・ ・ ・ ・ ・ ・ ・v王 ・|二
+| ・ ・ ・ ・ ・ ・ ・v歩 ・|三
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|四
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|五
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|六
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|七
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|八
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|九
++---------------------------+
+下手の持駒：金　歩
+";
+    let ban = parse(bod).unwrap();
+    assert_eq!(
+        ban.to_sfen(),
+        "7nl/7k1/7p1/9/9/9/9/9/9 b GP2rb3g3s3n3l16p 1"
+    );
+    assert!(ban.turn);
+
+    let error = |bod: &str| match parse(bod) {
+        Err(Error::Kifu(e)) => e.line,
+        _ => panic!("{}", bod),
+    };
+    let rows = bod.lines().collect::<Vec<_>>();
+    assert_eq!(error(&rows[..12].join("\n")), 12);
+    assert_eq!(error(&bod.replace("v王 ・|二", "v王 x|二")), 6);
+    assert_eq!(error(&bod.replace("金　歩", "金　王")), 15);
+    assert_eq!(error(&bod.replace("歩十六", "歩二十")), 2);
+}
@@ -0,0 +1,628 @@
+//! CSA format, e.g. `+7776FU`
+
+use std::time::Duration;
+
+use super::{build_ban, kifu_error, RecordBuilder};
+use crate::{
+    ban2::Ban2 as Ban,
+    error::Error,
+    handicap::Handicap,
+    record::{GameRecord, GameResult, MoveRecord},
+    Hand, Piece,
+};
+
+/// Pieces in CSA with the number of each piece in a game
+const PIECES: [(&str, Piece, bool, usize); 14] = [
+    ("FU", Piece::Fu, false, 18),
+    ("KY", Piece::Kyosha, false, 4),
+    ("KE", Piece::Keima, false, 4),
+    ("GI", Piece::Gin, false, 4),
+    ("KI", Piece::Kin, false, 4),
+    ("KA", Piece::Kaku, false, 2),
+    ("HI", Piece::Hisha, false, 2),
+    ("OU", Piece::Ou, false, 2),
+    ("TO", Piece::Fu, true, 0),
+    ("NY", Piece::Kyosha, true, 0),
+    ("NK", Piece::Keima, true, 0),
+    ("NG", Piece::Gin, true, 0),
+    ("UM", Piece::Kaku, true, 0),
+    ("RY", Piece::Hisha, true, 0),
+];
+
+/// CSA headers with the keys used in `GameRecord`
+const HEADERS: [(&str, &str); 8] = [
+    ("N+", "先手"),
+    ("N-", "後手"),
+    ("$EVENT", "棋戦"),
+    ("$SITE", "場所"),
+    ("$START_TIME", "開始日時"),
+    ("$END_TIME", "終了日時"),
+    ("$TIME_LIMIT", "持ち時間"),
+    ("$OPENING", "戦型"),
+];
+
+/// Results in CSA. `%+ILLEGAL_ACTION` and `%-ILLEGAL_ACTION` depend on the turn
+const RESULTS: [(&str, GameResult); 9] = [
+    ("%TORYO", GameResult::Resign),
+    ("%TSUMI", GameResult::Checkmate),
+    ("%SENNICHITE", GameResult::Sennichite),
+    ("%JISHOGI", GameResult::Jishogi),
+    ("%HIKIWAKE", GameResult::Jishogi),
+    ("%TIME_UP", GameResult::Timeout),
+    ("%KACHI", GameResult::Declaration),
+    ("%ILLEGAL_MOVE", GameResult::IllegalMove),
+    ("%CHUDAN", GameResult::Interrupted),
+];
+
+pub(crate) fn piece_name(piece: Piece, promoted: bool) -> &'static str {
+    PIECES
+        .iter()
+        .find(|(_, p, pr, _)| *p == piece && *pr == promoted)
+        .map(|(name, ..)| *name)
+        .unwrap()
+}
+
+pub(crate) fn parse_piece_name(s: &str) -> Option<(Piece, bool)> {
+    PIECES
+        .iter()
+        .find(|(name, ..)| *name == s)
+        .map(|(_, piece, promoted, _)| (*piece, *promoted))
+}
+
+fn sign(turn: bool) -> char {
+    if turn {
+        '+'
+    } else {
+        '-'
+    }
+}
+
+/// Write a move in CSA like `+7776FU`, with the piece after the move
+pub(crate) fn format_move(ban: &Ban, hand: &Hand) -> String {
+    let (ax, ay) = hand.destination();
+    match *hand {
+        Hand::Movement {
+            x, y, with_promote, ..
+        } => {
+            let (piece, promoted) = ban
+                .get_position(x, y)
+                .map(|p| (p.piece, p.promoted))
+                .unwrap_or((Piece::Fu, false));
+            format!(
+                "{}{}{}{}{}{}",
+                sign(ban.turn),
+                x,
+                y,
+                ax,
+                ay,
+                piece_name(piece, promoted || with_promote)
+            )
+        }
+        Hand::Putting { piece, .. } => {
+            format!(
+                "{}00{}{}{}",
+                sign(ban.turn),
+                ax,
+                ay,
+                piece_name(piece, false)
+            )
+        }
+    }
+}
+
+/// Parse a move in CSA like `+7776FU` in given position
+pub(crate) fn parse_move(ban: &Ban, s: &str) -> Result<Hand, String> {
+    let invalid = || format!("invalid move {}", s);
+    let chars = s.chars().collect::<Vec<_>>();
+    if !s.is_ascii() || chars.len() != 7 || !chars[1..5].iter().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if chars[0] != sign(ban.turn) {
+        return Err(format!("unexpected turn at {}", s));
+    }
+    let digit = |i: usize| chars[i] as usize - '0' as usize;
+    let (x, y, ax, ay) = (digit(1), digit(2), digit(3), digit(4));
+    let (piece, promoted) = parse_piece_name(&s[5..]).ok_or_else(invalid)?;
+    if !(1..=9).contains(&ax) || !(1..=9).contains(&ay) {
+        return Err(invalid());
+    }
+
+    if (x, y) == (0, 0) {
+        if promoted {
+            return Err(invalid());
+        }
+        return Ok(Hand::Putting {
+            piece,
+            x: ax,
+            y: ay,
+        });
+    }
+    let moving = ban
+        .get_position(x, y)
+        .filter(|p| p.piece == piece && (p.promoted == promoted || !p.promoted))
+        .ok_or_else(|| format!("no {} at {}{}", &s[5..], x, y))?;
+    Ok(Hand::Movement {
+        x,
+        y,
+        dx: ax as isize - x as isize,
+        dy: ay as isize - y as isize,
+        with_promote: promoted && !moving.promoted,
+    })
+}
+
+/// Initial position being read from `PI` or `P1`..`P9` lines
+struct Setup {
+    /// Pieces on board as (turn, piece, promoted), indexed like `Ban2`
+    board: [Option<(bool, Piece, bool)>; 81],
+    /// Pieces in hand as (turn, piece)
+    havings: Vec<(bool, Piece)>,
+}
+
+impl Setup {
+    fn hirate() -> Self {
+        let ban = Ban::new();
+        let mut board = [None; 81];
+        for x in 1..=9 {
+            for y in 1..=9 {
+                board[(x - 1) + (y - 1) * 9] = ban
+                    .get_position(x, y)
+                    .map(|p| (p.turn, p.piece, p.promoted));
+            }
+        }
+        Self {
+            board,
+            havings: Vec::new(),
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            board: [None; 81],
+            havings: Vec::new(),
+        }
+    }
+
+    /// Parse `PI` followed by removed pieces like `82HI22KA`
+    fn parse_pi(line: &str) -> Result<Self, String> {
+        let mut setup = Self::hirate();
+        let chars = line[2..].chars().collect::<Vec<_>>();
+        for removed in chars.chunks(4) {
+            let removed = removed.iter().collect::<String>();
+            let position = removed
+                .get(..2)
+                .and_then(|p| p.parse::<usize>().ok())
+                .filter(|p| (1..=9).contains(&(p / 10)) && (1..=9).contains(&(p % 10)))
+                .ok_or_else(|| format!("invalid position {}", removed))?;
+            let index = (position / 10 - 1) + (position % 10 - 1) * 9;
+            let piece = removed.get(2..).and_then(parse_piece_name);
+            match setup.board[index] {
+                Some((_, p, promoted)) if Some((p, promoted)) == piece => setup.board[index] = None,
+                _ => return Err(format!("cannot remove {}", removed)),
+            }
+        }
+        Ok(setup)
+    }
+
+    /// Parse a rank line like `P1-KY-KE * ...`
+    fn parse_rank(&mut self, line: &str) -> Result<(), String> {
+        let invalid = || format!("invalid rank {}", line);
+        let y = line
+            .get(1..2)
+            .and_then(|y| y.parse::<usize>().ok())
+            .filter(|y| (1..=9).contains(y))
+            .ok_or_else(invalid)?;
+        // trailing spaces of the empty squares are often trimmed
+        let rest = line.get(2..).ok_or_else(invalid)?;
+        let chars = format!("{:27}", rest).chars().collect::<Vec<_>>();
+        if chars.len() != 27 {
+            return Err(invalid());
+        }
+        for (i, square) in chars.chunks(3).enumerate() {
+            let square = square.iter().collect::<String>();
+            let x = 9 - i;
+            self.board[(x - 1) + (y - 1) * 9] = match square.as_str() {
+                " * " => None,
+                _ => {
+                    let turn = match square.chars().next() {
+                        Some('+') => true,
+                        Some('-') => false,
+                        _ => return Err(invalid()),
+                    };
+                    let (piece, promoted) = parse_piece_name(&square[1..]).ok_or_else(invalid)?;
+                    Some((turn, piece, promoted))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Parse pieces like `P+00FU00KA` or `P-00AL`. Pieces on board are not supported
+    fn parse_pieces(&mut self, line: &str) -> Result<(), String> {
+        let turn = line.starts_with("P+");
+        let chars = line[2..].chars().collect::<Vec<_>>();
+        for piece in chars.chunks(4) {
+            let piece = piece.iter().collect::<String>();
+            if !piece.starts_with("00") {
+                return Err(format!("unsupported piece {}", piece));
+            }
+            if &piece[2..] == "AL" {
+                self.add_rest(turn);
+                continue;
+            }
+            match parse_piece_name(&piece[2..]) {
+                Some((piece, false)) if piece != Piece::Ou => self.havings.push((turn, piece)),
+                _ => return Err(format!("invalid piece {}", piece)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Give all pieces not placed yet to the turn except Ou
+    fn add_rest(&mut self, turn: bool) {
+        for (_, piece, _, total) in PIECES.iter().filter(|p| !p.2 && p.1 != Piece::Ou) {
+            let used = self
+                .board
+                .iter()
+                .flatten()
+                .filter(|p| p.1 == *piece)
+                .count()
+                + self.havings.iter().filter(|p| p.1 == *piece).count();
+            for _ in used..*total {
+                self.havings.push((turn, *piece));
+            }
+        }
+    }
+
+    fn into_ban(self, turn: bool) -> Result<Ban, String> {
+        build_ban(&self.board, &self.havings, turn)
+    }
+}
+
+/// Parse a CSA kifu. Only the first game is read when the file has several games
+pub fn parse(csa: &str) -> Result<GameRecord, Error> {
+    let mut headers = Vec::new();
+    let mut comments = Vec::new();
+    let mut setup: Option<Setup> = None;
+    let mut builder: Option<RecordBuilder> = None;
+
+    'lines: for (i, line) in csa.lines().enumerate() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        if let (Some(evaluation), Some(builder)) = (line.strip_prefix("'**"), builder.as_mut()) {
+            // floodgate writes the evaluation and the expected moves like `'** 30 -3334FU`
+            let evaluation = evaluation
+                .split_whitespace()
+                .next()
+                .and_then(|e| e.parse().ok());
+            if let Some(mv) = builder.current_line().last_mut() {
+                mv.evaluation = evaluation;
+            }
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('\'') {
+            let comment = comment.strip_prefix('*').unwrap_or(comment).to_string();
+            match builder.as_mut() {
+                Some(builder) => builder.comment(comment),
+                None => comments.push(comment),
+            }
+            continue;
+        }
+
+        for statement in line.split(',') {
+            let error = |e| kifu_error(i, e);
+            if statement.is_empty() || statement.starts_with('V') {
+                continue;
+            } else if statement == "/" {
+                break 'lines;
+            } else if let Some(header) = parse_header(statement) {
+                headers.push(header);
+            } else if !statement.is_ascii() {
+                return Err(error(format!("invalid line {}", statement)));
+            } else if statement.starts_with("PI") {
+                setup = Some(Setup::parse_pi(statement).map_err(error)?);
+            } else if statement.starts_with("P+") || statement.starts_with("P-") {
+                setup
+                    .get_or_insert_with(Setup::empty)
+                    .parse_pieces(statement)
+                    .map_err(error)?;
+            } else if statement.starts_with('P') {
+                setup
+                    .get_or_insert_with(Setup::empty)
+                    .parse_rank(statement)
+                    .map_err(error)?;
+            } else if statement == "+" || statement == "-" {
+                if builder.is_some() {
+                    return Err(error("turn after moves".to_string()));
+                }
+                let initial = setup
+                    .take()
+                    .unwrap_or_else(Setup::hirate)
+                    .into_ban(statement == "+")
+                    .map_err(error)?;
+                let mut record = GameRecord::new(initial);
+                record.headers = std::mem::take(&mut headers);
+                record.comments = std::mem::take(&mut comments);
+                builder = Some(RecordBuilder::new(record));
+            } else {
+                let builder = builder
+                    .as_mut()
+                    .ok_or_else(|| error(format!("no turn before {}", statement)))?;
+                parse_statement(builder, statement).map_err(error)?;
+            }
+        }
+    }
+
+    match builder {
+        Some(builder) => Ok(builder.finish()),
+        None => Err(kifu_error(csa.lines().count().saturating_sub(1), "no turn")),
+    }
+}
+
+fn parse_header(statement: &str) -> Option<(String, String)> {
+    let (key, value) = if statement.starts_with("N+") || statement.starts_with("N-") {
+        statement.split_at(2)
+    } else if statement.starts_with('$') {
+        let (key, value) = statement.split_once(':')?;
+        (key, value)
+    } else {
+        return None;
+    };
+    let key = HEADERS
+        .iter()
+        .find(|(csa, _)| *csa == key)
+        .map(|(_, key)| *key)
+        .unwrap_or(key);
+    Some((key.to_string(), value.to_string()))
+}
+
+/// Parse a move, time or result
+fn parse_statement(builder: &mut RecordBuilder, statement: &str) -> Result<(), String> {
+    if statement.starts_with('+') || statement.starts_with('-') {
+        let hand = parse_move(&builder.ban, statement)?;
+        builder.push(MoveRecord::new(hand))
+    } else if let Some(time) = statement.strip_prefix('T') {
+        let time = time
+            .parse::<f64>()
+            .ok()
+            .and_then(|t| Duration::try_from_secs_f64(t).ok())
+            .ok_or_else(|| format!("invalid time {}", statement))?;
+        match builder.current_line().last_mut() {
+            Some(mv) => mv.time = Some(time),
+            None => return Err(format!("no move for {}", statement)),
+        }
+        Ok(())
+    } else if statement.starts_with('%') {
+        builder.record.result = Some(parse_result(statement, builder.ban.turn)?);
+        Ok(())
+    } else {
+        Err(format!("invalid line {}", statement))
+    }
+}
+
+/// Parse a result seen from the turn after the last move
+pub(crate) fn parse_result(statement: &str, turn: bool) -> Result<GameResult, String> {
+    if let Some(illegal) = statement.strip_suffix("ILLEGAL_ACTION") {
+        return match illegal {
+            "%+" | "%-" if (illegal == "%+") == turn => Ok(GameResult::IllegalMove),
+            "%+" | "%-" => Ok(GameResult::IllegalWin),
+            _ => Err(format!("unknown result {}", statement)),
+        };
+    }
+    RESULTS
+        .iter()
+        .find(|(name, _)| *name == statement)
+        .map(|(_, result)| *result)
+        .ok_or_else(|| format!("unknown result {}", statement))
+}
+
+pub(crate) fn format_result(result: GameResult, turn: bool) -> String {
+    match result {
+        GameResult::IllegalWin => format!("%{}ILLEGAL_ACTION", sign(!turn)),
+        _ => RESULTS
+            .iter()
+            .find(|(_, r)| *r == result)
+            .map(|(name, _)| name.to_string())
+            .unwrap(),
+    }
+}
+
+fn write_position(ban: &Ban, out: &mut String) {
+    if Handicap::from_ban(ban).is_some() {
+        // handicaps are written as pieces removed from hirate
+        let hirate = Ban::new();
+        out.push_str("PI");
+        for y in 1..=9 {
+            for x in 1..=9 {
+                if let (Some(p), None) = (hirate.get_position(x, y), ban.get_position(x, y)) {
+                    out.push_str(&format!("{}{}{}", x, y, piece_name(p.piece, false)));
+                }
+            }
+        }
+        out.push('\n');
+    } else {
+        for y in 1..=9 {
+            out.push_str(&format!("P{}", y));
+            for x in (1..=9).rev() {
+                match ban.get_position(x, y) {
+                    Some(p) => {
+                        out.push(sign(p.turn));
+                        out.push_str(piece_name(p.piece, p.promoted));
+                    }
+                    None => out.push_str(" * "),
+                }
+            }
+            out.push('\n');
+        }
+        for (turn, pieces) in [(true, &ban.primary_pieces), (false, &ban.secondary_pieces)] {
+            if pieces.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("P{}", sign(turn)));
+            for piece in pieces {
+                out.push_str(&format!("00{}", piece_name(*piece, false)));
+            }
+            out.push('\n');
+        }
+    }
+    out.push(sign(ban.turn));
+    out.push('\n');
+}
+
+/// Write given record in CSA. Variations are not written as CSA has no way to write them
+pub fn write(record: &GameRecord) -> String {
+    let mut out = String::from("V2.2\n");
+    for (key, value) in &record.headers {
+        match HEADERS.iter().find(|(_, k)| k == key) {
+            Some((csa, _)) if csa.starts_with('N') => out.push_str(&format!("{}{}\n", csa, value)),
+            Some((csa, _)) => out.push_str(&format!("{}:{}\n", csa, value)),
+            None if key.starts_with('$') => out.push_str(&format!("{}:{}\n", key, value)),
+            None => {}
+        }
+    }
+    for comment in &record.comments {
+        out.push_str(&format!("'{}\n", comment));
+    }
+    write_position(&record.initial, &mut out);
+
+    let mut ban = record.initial.clone();
+    for mv in &record.moves {
+        out.push_str(&format_move(&ban, &mv.hand));
+        out.push('\n');
+        if let Some(time) = mv.time {
+            out.push_str(&format!("T{}\n", super::whole_seconds(time)));
+        }
+        if let Some(evaluation) = mv.evaluation {
+            out.push_str(&format!("'** {}\n", evaluation));
+        }
+        for comment in &mv.comments {
+            out.push_str(&format!("'*{}\n", comment));
+        }
+        if ban.apply_hand(&mv.hand).is_err() {
+            break;
+        }
+    }
+    if let Some(result) = record.result {
+        out.push_str(&format_result(result, ban.turn));
+        out.push('\n');
+    }
+    out
+}
+
+#[test]
+fn csa_round_trip() {
+    let csa = "V2.2
+N+Alice
+N-Bob
+$EVENT:floodgate
+$START_TIME:2020/01/01 10:00:00
+'opening comment
+PI
++
++7776FU
+T10
+'*good move
+-3334FU
+T5
+'** -30
++8822UM
+T60
+-3122GI
+T3
++0045KA
+T1
+%TORYO
+";
+    let record = parse(csa).unwrap();
+    assert_eq!(record.header("先手"), Some("Alice"));
+    assert_eq!(record.header("棋戦"), Some("floodgate"));
+    assert_eq!(record.comments, vec!["opening comment".to_string()]);
+    assert_eq!(record.moves.len(), 5);
+    assert_eq!(record.moves[0].comments, vec!["good move".to_string()]);
+    assert_eq!(record.moves[2].time, Some(Duration::from_secs(60)));
+    assert_eq!(record.moves[1].evaluation, Some(-30));
+    assert_eq!(record.result, Some(GameResult::Resign));
+    assert_eq!(
+        record.last_ban().unwrap().to_sfen(),
+        "lnsgkg1nl/1r5s1/pppppp1pp/6p2/5B3/2P6/PP1PPPPPP/7R1/LNSGKGSNL w b 6"
+    );
+    assert_eq!(write(&record), csa);
+    let with_pv = csa.replace("'** -30", "'** -30 +8822UM -3122GI");
+    // fractions of V3 times are rounded since V2.2 has none
+    let fractional = parse(&csa.replace("T10\n", "T9.5\n")).unwrap();
+    assert_eq!(fractional.moves[0].time, Some(Duration::from_millis(9500)));
+    assert_eq!(write(&fractional), csa);
+    assert_eq!(parse(&with_pv), Ok(record.clone()));
+
+    // KIF has no place for evaluations
+    let kif = super::kif::write(&record);
+    let mut moves = record.moves.clone();
+    moves[1].evaluation = None;
+    assert_eq!(super::kif::parse(&kif).unwrap().moves, moves);
+}
+
+#[test]
+fn csa_position() {
+    let csa = "P1-KY-KE-GI-KI-OU-KI-GI-KE-KY
+P2 *  *  *  *  *  *  *  *  * 
+P3-FU-FU-FU-FU-FU-FU-FU-FU-FU
+P4 *  *  *  *  *  *  *  *  * 
+P5 *  *  *  *  *  *  *  *  * 
+P6 *  *  *  *  *  *  *  *  * 
+P7+FU+FU+FU+FU+FU+FU+FU+FU+FU
+P8 * +KA *  *  *  *  * +HI * 
+P9+KY+KE+GI+KI+OU+KI+GI+KE+KY
+-
+-5142OU,+7776FU
+%CHUDAN
+";
+    let record = parse(csa).unwrap();
+    assert_eq!(
+        record.initial.to_sfen(),
+        "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"
+    );
+    assert_eq!(record.moves.len(), 2);
+    assert_eq!(record.result, Some(GameResult::Interrupted));
+    assert_eq!(parse("PI82HI22KA\n-\n").unwrap().initial, record.initial);
+    let written = write(&record);
+    assert!(written.contains("\nPI22KA82HI\n-\n"));
+    assert_eq!(parse(&written), Ok(record));
+
+    let record = parse("P1 *  *  *  *  *  *  *  * -OU\nP+00KI\nP-00AL\n+\n").unwrap();
+    assert_eq!(record.initial.primary_pieces, vec![Piece::Kin]);
+    assert_eq!(record.initial.secondary_pieces.len(), 37);
+
+    assert_eq!(
+        parse_result("%+ILLEGAL_ACTION", true),
+        Ok(GameResult::IllegalMove)
+    );
+    assert_eq!(
+        parse_result("%+ILLEGAL_ACTION", false),
+        Ok(GameResult::IllegalWin)
+    );
+    assert_eq!(
+        format_result(GameResult::IllegalWin, false),
+        "%+ILLEGAL_ACTION"
+    );
+}
+
+#[test]
+fn csa_errors() {
+    let error = |csa: &str| match parse(csa) {
+        Err(Error::Kifu(e)) => e.line,
+        _ => panic!("{}", csa),
+    };
+    assert_eq!(error("PI\n+\n-3334FU\n"), 3);
+    assert_eq!(error("PI\n+\n+7775FU\n"), 3);
+    assert_eq!(error("PI\n+\n+7776KA\n"), 3);
+    assert_eq!(error("PI\n+\n+7776FU\n%WIN\n"), 4);
+    assert_eq!(error("PI\n+\nT10\n"), 3);
+    assert_eq!(error("PI55FU\n+\n"), 1);
+    assert_eq!(error("P0 *\n+\n"), 1);
+    assert_eq!(error("+7776FU\n"), 1);
+    assert_eq!(error("PI\n+\n+７７76FU\n"), 3);
+    assert_eq!(error("N+Alice\n"), 1);
+    assert_eq!(error("P\n+\n"), 1);
+    assert_eq!(error("P１ *\n+\n"), 1);
+    assert_eq!(error("PI\n+\n+7776FU\nT1e20\n"), 4);
+    assert_eq!(error("PI\n+\n+7776FU\nTinf\n"), 4);
+    assert_eq!(error("PI\n+\n+7776FU\nT-1\n"), 4);
+}
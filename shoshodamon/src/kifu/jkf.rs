@@ -0,0 +1,573 @@
+//! JSON Kifu Format, e.g. `{"header":{},"moves":[{},{"move":{...}}]}`
+
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{build_ban, csa, japanese, LineStart};
+use crate::{
+    ban2::Ban2 as Ban,
+    error::{Error, KifuError},
+    handicap::Handicap,
+    record::{GameRecord, GameResult, MoveRecord},
+    Hand, Piece,
+};
+
+/// Presets of JKF with their handicaps
+const PRESETS: [(&str, Handicap); 16] = [
+    ("HIRATE", Handicap::Hirate),
+    ("KY", Handicap::Lance),
+    ("KY_R", Handicap::RightLance),
+    ("KA", Handicap::Bishop),
+    ("HI", Handicap::Rook),
+    ("HIKY", Handicap::RookLance),
+    ("2", Handicap::TwoPieces),
+    ("3", Handicap::ThreePieces),
+    ("4", Handicap::FourPieces),
+    ("5", Handicap::FivePieces),
+    ("5_L", Handicap::LeftFivePieces),
+    ("6", Handicap::SixPieces),
+    ("7_L", Handicap::LeftSevenPieces),
+    ("7_R", Handicap::RightSevenPieces),
+    ("8", Handicap::EightPieces),
+    ("10", Handicap::TenPieces),
+];
+
+/// Relative notations of JKF with the ones of KI2
+const RELATIVES: [(char, char); 7] = [
+    ('L', '左'),
+    ('C', '直'),
+    ('R', '右'),
+    ('U', '上'),
+    ('M', '寄'),
+    ('D', '引'),
+    ('H', '打'),
+];
+
+/// Headers keeping their order
+#[derive(Debug, Default)]
+struct Header(Vec<(String, String)>);
+
+impl Serialize for Header {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Header {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeaderVisitor;
+
+        impl<'de> Visitor<'de> for HeaderVisitor {
+            type Value = Header;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map of strings")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Header, A::Error> {
+                let mut headers = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    headers.push(entry);
+                }
+                Ok(Header(headers))
+            }
+        }
+
+        deserializer.deserialize_map(HeaderVisitor)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Jkf {
+    #[serde(default)]
+    header: Header,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    initial: Option<Initial>,
+    moves: Vec<MoveFormat>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Initial {
+    preset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<State>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    /// 0 for sente
+    color: u8,
+    /// `board[x - 1][y - 1]`
+    board: Vec<Vec<Cell>>,
+    /// Pieces in hand of sente and gote
+    hands: Vec<BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cell {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MoveFormat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<Vec<String>>,
+    #[serde(rename = "move", skip_serializing_if = "Option::is_none")]
+    mv: Option<Move>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<Time>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    special: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forks: Option<Vec<Vec<MoveFormat>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Move {
+    color: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<Place>,
+    to: Place,
+    piece: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    same: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    promote: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Place {
+    x: usize,
+    y: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Time {
+    now: MinutesSeconds,
+    total: HoursMinutesSeconds,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MinutesSeconds {
+    m: u64,
+    s: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HoursMinutesSeconds {
+    h: u64,
+    m: u64,
+    s: u64,
+}
+
+fn color(turn: bool) -> u8 {
+    if turn {
+        0
+    } else {
+        1
+    }
+}
+
+fn jkf_error(message: impl Into<String>) -> Error {
+    Error::Kifu(KifuError {
+        line: 0,
+        message: message.into(),
+    })
+}
+
+/// Parse a JKF kifu
+pub fn parse(jkf: &str) -> Result<GameRecord, Error> {
+    let jkf: Jkf = serde_json::from_str(jkf).map_err(|e| {
+        Error::Kifu(KifuError {
+            line: e.line(),
+            message: e.to_string(),
+        })
+    })?;
+
+    let initial = match &jkf.initial {
+        None => Ban::new(),
+        Some(initial) => read_initial(initial).map_err(jkf_error)?,
+    };
+    let mut record = GameRecord::new(initial);
+    record.headers = jkf.header.0;
+    let start = LineStart::new(&record.initial);
+    let (moves, result) =
+        read_moves(&jkf.moves, &start, &mut record.comments, 1).map_err(jkf_error)?;
+    record.moves = moves;
+    record.result = result;
+    Ok(record)
+}
+
+fn read_initial(initial: &Initial) -> Result<Ban, String> {
+    if let Some(handicap) = PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == initial.preset)
+        .map(|(_, handicap)| *handicap)
+    {
+        return Ok(handicap.ban());
+    }
+    let state = match (initial.preset.as_str(), &initial.data) {
+        ("OTHER", Some(state)) => state,
+        _ => return Err(format!("unknown preset {}", initial.preset)),
+    };
+    if state.board.len() != 9 || state.board.iter().any(|file| file.len() != 9) {
+        return Err("board must be 9x9".to_string());
+    }
+    if state.hands.len() != 2 {
+        return Err("hands must be of sente and gote".to_string());
+    }
+
+    let mut board = [None; 81];
+    for x in 1..=9 {
+        for y in 1..=9 {
+            let cell = &state.board[x - 1][y - 1];
+            board[(x - 1) + (y - 1) * 9] = match (cell.color, &cell.kind) {
+                (Some(color), Some(kind)) => {
+                    let (piece, promoted) = csa::parse_piece_name(kind)
+                        .ok_or_else(|| format!("invalid piece {}", kind))?;
+                    Some((color == 0, piece, promoted))
+                }
+                (None, None) => None,
+                _ => return Err(format!("invalid cell at {}{}", x, y)),
+            };
+        }
+    }
+    let mut havings = Vec::new();
+    for (i, hand) in state.hands.iter().enumerate() {
+        for (kind, count) in hand {
+            match csa::parse_piece_name(kind) {
+                _ if *count > 18 => return Err(format!("too many {}", kind)),
+                Some((piece, false)) => havings.extend((0..*count).map(|_| (i == 0, piece))),
+                _ => return Err(format!("invalid piece {}", kind)),
+            }
+        }
+    }
+    build_ban(&board, &havings, state.color == 0)
+}
+
+/// Read moves of a line whose first move is the `ply`th
+fn read_moves(
+    entries: &[MoveFormat],
+    start: &LineStart,
+    comments: &mut Vec<String>,
+    ply: usize,
+) -> Result<(Vec<MoveRecord>, Option<GameResult>), String> {
+    let mut moves: Vec<MoveRecord> = Vec::new();
+    let mut result = None;
+    let mut state = start.clone();
+    for entry in entries {
+        let entry_comments = entry.comments.clone().unwrap_or_default();
+        let mv = match &entry.mv {
+            Some(mv) => mv,
+            None => {
+                match moves.last_mut() {
+                    Some(last) => last.comments.extend(entry_comments),
+                    None => comments.extend(entry_comments),
+                }
+                if let Some(special) = &entry.special {
+                    result = Some(csa::parse_result(&format!("%{}", special), state.ban.turn)?);
+                }
+                continue;
+            }
+        };
+        let number = ply + moves.len();
+        if mv.color != color(state.ban.turn) {
+            return Err(format!("unexpected color at move {}", number));
+        }
+        let (piece, promoted) = csa::parse_piece_name(&mv.piece)
+            .ok_or_else(|| format!("invalid piece {} at move {}", mv.piece, number))?;
+        let hand = match mv.from {
+            Some(from) => Hand::Movement {
+                x: from.x,
+                y: from.y,
+                dx: mv.to.x as isize - from.x as isize,
+                dy: mv.to.y as isize - from.y as isize,
+                with_promote: mv.promote == Some(true),
+            },
+            None if !promoted => Hand::Putting {
+                piece,
+                x: mv.to.x,
+                y: mv.to.y,
+            },
+            None => return Err(format!("cannot put promoted piece at move {}", number)),
+        };
+
+        let mut record = MoveRecord::new(hand);
+        record.comments = entry_comments;
+        record.time = entry
+            .time
+            .as_ref()
+            .map(|t| {
+                t.now
+                    .m
+                    .checked_mul(60)
+                    .and_then(|s| s.checked_add(t.now.s))
+                    .map(Duration::from_secs)
+                    .ok_or_else(|| format!("invalid time at move {}", number))
+            })
+            .transpose()?;
+        for fork in entry.forks.iter().flatten() {
+            let (variation, _) = read_moves(fork, &state, &mut Vec::new(), number)?;
+            record.variations.push(variation);
+        }
+        state = state
+            .next(&record)
+            .ok_or_else(|| format!("illegal move at move {}", number))?;
+        moves.push(record);
+    }
+    Ok((moves, result))
+}
+
+/// Write given record in JKF
+pub fn write(record: &GameRecord) -> String {
+    let initial = match Handicap::from_ban(&record.initial) {
+        Some(handicap) => PRESETS
+            .iter()
+            .find(|(_, h)| *h == handicap)
+            .map(|(preset, _)| Initial {
+                preset: preset.to_string(),
+                data: None,
+            }),
+        None => Some(write_initial(&record.initial)),
+    };
+    let mut first = MoveFormat::default();
+    if !record.comments.is_empty() {
+        first.comments = Some(record.comments.clone());
+    }
+    let start = LineStart::new(&record.initial);
+    let mut moves = vec![first];
+    let end = write_moves(&record.moves, &start, &mut moves);
+    if let Some(result) = record.result {
+        moves.push(MoveFormat {
+            special: Some(csa::format_result(result, end.ban.turn)[1..].to_string()),
+            ..MoveFormat::default()
+        });
+    }
+
+    let headers = record
+        .headers
+        .iter()
+        .filter(|(key, _)| key != "手合割")
+        .cloned()
+        .collect();
+    let jkf = Jkf {
+        header: Header(headers),
+        initial,
+        moves,
+    };
+    serde_json::to_string(&jkf).unwrap()
+}
+
+fn write_initial(ban: &Ban) -> Initial {
+    let board = (1..=9)
+        .map(|x| {
+            (1..=9)
+                .map(|y| match ban.get_position(x, y) {
+                    Some(p) => Cell {
+                        color: Some(color(p.turn)),
+                        kind: Some(csa::piece_name(p.piece, p.promoted).to_string()),
+                    },
+                    None => Cell::default(),
+                })
+                .collect()
+        })
+        .collect();
+    let hands = [&ban.primary_pieces, &ban.secondary_pieces]
+        .iter()
+        .map(|pieces| {
+            let mut hand = BTreeMap::new();
+            for piece in [
+                Piece::Fu,
+                Piece::Kyosha,
+                Piece::Keima,
+                Piece::Gin,
+                Piece::Kin,
+                Piece::Kaku,
+                Piece::Hisha,
+            ]
+            .iter()
+            {
+                let count = pieces.iter().filter(|p| *p == piece).count();
+                hand.insert(csa::piece_name(*piece, false).to_string(), count);
+            }
+            hand
+        })
+        .collect();
+    Initial {
+        preset: "OTHER".to_string(),
+        data: Some(State {
+            color: color(ban.turn),
+            board,
+            hands,
+        }),
+    }
+}
+
+/// Write moves of a line and get the state after them
+fn write_moves(moves: &[MoveRecord], start: &LineStart, out: &mut Vec<MoveFormat>) -> LineStart {
+    let mut state = start.clone();
+    for mv in moves {
+        let next = match state.next(mv) {
+            Some(next) => next,
+            None => break,
+        };
+        let ban = &state.ban;
+        let (x, y) = mv.hand.destination();
+        let (piece, promoted, from) = match mv.hand {
+            Hand::Movement { x, y, .. } => {
+                let p = ban.get_position(x, y).unwrap();
+                (p.piece, p.promoted, Some(Place { x, y }))
+            }
+            Hand::Putting { piece, .. } => (piece, false, None),
+        };
+        let promote = match mv.hand {
+            Hand::Movement {
+                with_promote: true, ..
+            } => Some(true),
+            _ if japanese::is_narazu(ban, &mv.hand) => Some(false),
+            _ => None,
+        };
+        let relative = japanese::relative(ban, &mv.hand)
+            .chars()
+            .filter_map(|c| RELATIVES.iter().find(|(_, k)| *k == c).map(|(j, _)| *j))
+            .collect::<String>();
+        let time = mv.time.map(|time| {
+            let (now, total) = (
+                super::whole_seconds(time),
+                super::whole_seconds(next.times[ban.turn as usize]),
+            );
+            Time {
+                now: MinutesSeconds {
+                    m: now / 60,
+                    s: now % 60,
+                },
+                total: HoursMinutesSeconds {
+                    h: total / 3600,
+                    m: total / 60 % 60,
+                    s: total % 60,
+                },
+            }
+        });
+        let forks = mv
+            .variations
+            .iter()
+            .map(|variation| {
+                let mut fork = Vec::new();
+                write_moves(variation, &state, &mut fork);
+                fork
+            })
+            .collect::<Vec<_>>();
+
+        out.push(MoveFormat {
+            comments: Some(mv.comments.clone()).filter(|c| !c.is_empty()),
+            mv: Some(Move {
+                color: color(ban.turn),
+                from,
+                to: Place { x, y },
+                piece: csa::piece_name(piece, promoted).to_string(),
+                same: (state.last == Some((x, y))).then_some(true),
+                promote,
+                capture: ban
+                    .get_position(x, y)
+                    .map(|p| csa::piece_name(p.piece, p.promoted).to_string()),
+                relative: Some(relative).filter(|r| !r.is_empty()),
+            }),
+            time,
+            special: None,
+            forks: Some(forks).filter(|f| !f.is_empty()),
+        });
+        state = next;
+    }
+    state
+}
+
+#[test]
+fn jkf_round_trip() {
+    let kif = "手合割：平手
+先手：Alice
+後手：Bob
+*opening comment
+手数----指手---------消費時間--
+   1 ７六歩(77)     ( 0:10/00:00:10)
+*good move
+   2 ３四歩(33)     ( 0:05/00:00:05)
+   3 ２二角成(88)   ( 1:00/00:01:10)+
+   4 同　銀(31)     ( 0:03/00:00:08)
+   5 投了
+まで4手で後手の勝ち
+
+変化：3手
+   3 ６八銀(79)     ( 0:01/00:00:11)
+";
+    let record = super::kif::parse(kif).unwrap();
+    let jkf = write(&record);
+    assert!(
+        jkf.starts_with(r#"{"header":{"先手":"Alice","後手":"Bob"},"initial":{"preset":"HIRATE"}"#)
+    );
+    assert!(jkf.contains(r#"{"comments":["opening comment"]}"#));
+    assert!(jkf.contains(r#""move":{"color":1,"from":{"x":3,"y":1},"to":{"x":2,"y":2},"piece":"GI","same":true,"capture":"UM"}"#));
+    assert!(jkf.contains(r#""time":{"now":{"m":1,"s":0},"total":{"h":0,"m":1,"s":10}}"#));
+    assert!(jkf.ends_with(r#"{"special":"TORYO"}]}"#));
+
+    let mut expected = record.clone();
+    expected.headers.retain(|(key, _)| key != "手合割");
+    assert_eq!(parse(&jkf), Ok(expected));
+}
+
+#[test]
+fn jkf_initial() {
+    let jkf = r#"{
+        "header": {},
+        "initial": {"preset": "KA"},
+        "moves": [{}, {"move": {"color": 1, "from": {"x": 3, "y": 3}, "to": {"x": 3, "y": 4}, "piece": "FU"}}]
+    }"#;
+    let record = parse(jkf).unwrap();
+    assert_eq!(Handicap::from_ban(&record.initial), Some(Handicap::Bishop));
+    assert_eq!(record.moves.len(), 1);
+    assert_eq!(
+        write(&record),
+        r#"{"header":{},"initial":{"preset":"KA"},"moves":[{},{"move":{"color":1,"from":{"x":3,"y":3},"to":{"x":3,"y":4},"piece":"FU"}}]}"#
+    );
+
+    let ban = Ban::from_sfen("8k/9/9/9/9/9/9/9/K8 b RG2Pb 1").unwrap();
+    let record = GameRecord::new(ban);
+    let jkf = write(&record);
+    assert!(jkf.contains(r#""preset":"OTHER""#));
+    assert!(jkf.contains(r#"{"FU":2,"GI":0,"HI":1,"KA":0,"KE":0,"KI":1,"KY":0}"#));
+    assert_eq!(parse(&jkf), Ok(record));
+
+    let error = |jkf: &str| match parse(jkf) {
+        Err(Error::Kifu(e)) => e,
+        _ => panic!("{}", jkf),
+    };
+    assert_eq!(error("{\n\"moves\": [}").line, 2);
+    assert_eq!(error(r#"{"initial":{"preset":"9"},"moves":[]}"#).line, 0);
+    assert_eq!(
+        error(r#"{"moves":[{},{"move":{"color":1,"to":{"x":7,"y":6},"piece":"FU"}}]}"#).message,
+        "unexpected color at move 1"
+    );
+    assert_eq!(
+        error(r#"{"moves":[{},{"move":{"color":0,"from":{"x":7,"y":7},"to":{"x":7,"y":5},"piece":"FU"}}]}"#).message,
+        "illegal move at move 1"
+    );
+    assert_eq!(
+        error(r#"{"moves":[{},{"move":{"color":0,"from":{"x":7,"y":7},"to":{"x":7,"y":6},"piece":"FU"},"time":{"now":{"m":18446744073709551615,"s":0},"total":{"h":0,"m":0,"s":0}}}]}"#).message,
+        "invalid time at move 1"
+    );
+}
@@ -0,0 +1,193 @@
+//! KI2 format, e.g. `▲７六歩    △３四歩`
+
+use super::{
+    bod, check_header, first_ply, japanese, kifu_error, parse_variation, record_from_headers,
+    split_header, write_headers, write_summary, write_variations, LineStart, RecordBuilder,
+};
+use crate::{
+    error::Error,
+    record::{GameRecord, GameResult, MoveRecord},
+};
+
+const MARKS: [char; 4] = ['▲', '△', '☗', '☖'];
+
+/// Parse a KI2 kifu
+pub fn parse(ki2: &str) -> Result<GameRecord, Error> {
+    let mut headers = Vec::new();
+    let mut board = Vec::new();
+    let mut builder: Option<RecordBuilder> = None;
+
+    for (i, line) in ki2.lines().enumerate() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('&') {
+            continue;
+        }
+        if builder.is_none() {
+            if bod::is_bod_line(line) {
+                board.push((i, line));
+                continue;
+            }
+            if let Some((key, value)) = split_header(line) {
+                check_header(key, value).map_err(|e| kifu_error(i, e))?;
+                headers.push((key.to_string(), value.to_string()));
+                continue;
+            }
+            let record = record_from_headers(std::mem::take(&mut headers), &board, i)?;
+            builder = Some(RecordBuilder::new(record));
+        }
+        let builder = builder.as_mut().unwrap();
+
+        if let Some(comment) = line.strip_prefix('*') {
+            builder.comment(comment.to_string());
+        } else if let Some(summary) = line.strip_prefix("まで") {
+            if builder.is_main_line() {
+                builder.record.result = parse_summary(summary);
+            }
+        } else if let Some(ply) = parse_variation(line) {
+            builder.start_variation(ply).map_err(|e| kifu_error(i, e))?;
+        } else {
+            parse_move_line(builder, line).map_err(|e| kifu_error(i, e))?;
+        }
+    }
+
+    match builder {
+        Some(builder) => Ok(builder.finish()),
+        None => record_from_headers(headers, &board, ki2.lines().count()),
+    }
+}
+
+fn parse_move_line(builder: &mut RecordBuilder, line: &str) -> Result<(), String> {
+    if !line.starts_with(&MARKS[..]) {
+        return Err(format!("invalid line {}", line));
+    }
+    let mut rest = line;
+    while let Some(mark) = rest.chars().next() {
+        let body = &rest[mark.len_utf8()..];
+        let end = body.find(&MARKS[..]).unwrap_or(body.len());
+        let text = body[..end].trim_matches(|c: char| c.is_ascii_whitespace() || c == '　');
+        rest = &body[end..];
+
+        let sente = mark == '▲' || mark == '☗';
+        if sente != builder.ban.turn {
+            return Err(format!("unexpected turn at {}", text));
+        }
+        let hand = japanese::parse_ki2_move(&builder.ban, text, builder.last)?;
+        builder.push(MoveRecord::new(hand))?;
+    }
+    Ok(())
+}
+
+/// Parse the result from the summary like `64手で後手の勝ち`
+fn parse_summary(summary: &str) -> Option<GameResult> {
+    let results = [
+        ("千日手", GameResult::Sennichite),
+        ("持将棋", GameResult::Jishogi),
+        ("中断", GameResult::Interrupted),
+        ("反則勝ち", GameResult::IllegalWin),
+        ("反則負け", GameResult::IllegalMove),
+        ("切れ", GameResult::Timeout),
+        ("入玉", GameResult::Declaration),
+        ("詰", GameResult::Checkmate),
+        ("勝ち", GameResult::Resign),
+    ];
+    results
+        .iter()
+        .find(|(s, _)| summary.contains(s))
+        .map(|(_, r)| *r)
+}
+
+/// Moves written in a line
+const MOVES_PER_LINE: usize = 6;
+
+fn write_line(moves: &[MoveRecord], _: usize, start: &LineStart, out: &mut String) {
+    let mut state = start.clone();
+    // Width of the last move in the current output line, `None` at the start of line
+    let mut width = None;
+    let mut count = 0;
+    for mv in moves {
+        let text = japanese::format_ki2_move(&state.ban, &mv.hand, state.last);
+        let next = match state.next(mv) {
+            Some(next) => next,
+            None => break,
+        };
+        if let Some(width) = width {
+            out.push_str(&" ".repeat(12usize.saturating_sub(width)));
+        }
+        out.push(if state.ban.turn { '▲' } else { '△' });
+        out.push_str(&text);
+        width = Some(2 + text.chars().count() * 2);
+        count += 1;
+        if !mv.comments.is_empty() || count % MOVES_PER_LINE == 0 {
+            out.push('\n');
+            width = None;
+            count = 0;
+            for comment in &mv.comments {
+                out.push_str(&format!("*{}\n", comment));
+            }
+        }
+        state = next;
+    }
+    if width.is_some() {
+        out.push('\n');
+    }
+}
+
+/// Write given record in KI2
+pub fn write(record: &GameRecord) -> String {
+    let mut out = String::new();
+    write_headers(record, &mut out);
+    out.push('\n');
+
+    let start = LineStart::new(&record.initial);
+    write_line(&record.moves, first_ply(record), &start, &mut out);
+    write_summary(record, &mut out);
+    write_variations(
+        &record.moves,
+        first_ply(record),
+        &start,
+        &mut out,
+        &mut write_line,
+    );
+    out
+}
+
+#[test]
+fn ki2_round_trip() {
+    let ki2 = "手合割：平手
+先手：Alice
+後手：Bob
+
+▲７六歩    △３四歩    ▲４八銀    △４二銀    ▲５八金右  △５二金右
+▲６八銀    △３二金    ▲７八金    △８四歩
+*comment
+▲２二角成  △同　金
+まで12手で中断
+
+変化：11手
+▲６六歩    △８五歩
+";
+    let record = parse(ki2).unwrap();
+    assert_eq!(record.moves.len(), 12);
+    assert_eq!(record.moves[9].comments, vec!["comment".to_string()]);
+    assert_eq!(record.moves[10].variations.len(), 1);
+    assert_eq!(record.result, Some(GameResult::Interrupted));
+    assert_eq!(write(&record), ki2);
+
+    let kif = super::kif::write(&record);
+    assert_eq!(super::kif::parse(&kif), Ok(record));
+}
+
+#[test]
+fn ki2_handicap() {
+    let ki2 = "手合割：香落ち
+△３四歩    ▲７六歩    △８八角成  ▲同　銀
+まで4手で下手の勝ち
+";
+    let record = parse(ki2).unwrap();
+    assert!(!record.initial.turn);
+    assert_eq!(record.result, Some(GameResult::Resign));
+    assert_eq!(write(&record).replace("\n\n", "\n"), ki2);
+
+    assert!(parse("手合割：香落ち\n▲７六歩\n").is_err());
+    assert!(parse("▲５八金\n").is_err());
+}
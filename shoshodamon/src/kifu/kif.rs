@@ -0,0 +1,293 @@
+//! KIF format, e.g. `   1 ７六歩(77)   ( 0:01/00:00:01)`
+
+use std::time::Duration;
+
+use super::{
+    bod, check_header, first_ply, japanese, kifu_error, parse_variation, record_from_headers,
+    split_header, write_headers, write_summary, write_variations, LineStart, RecordBuilder,
+};
+use crate::{
+    error::Error,
+    record::{GameRecord, MoveRecord},
+};
+
+/// Parse a KIF kifu
+pub fn parse(kif: &str) -> Result<GameRecord, Error> {
+    let mut headers = Vec::new();
+    let mut board = Vec::new();
+    let mut builder: Option<RecordBuilder> = None;
+
+    for (i, line) in kif.lines().enumerate() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with('&')
+            || line.starts_with("まで")
+        {
+            continue;
+        }
+        if builder.is_none() {
+            if bod::is_bod_line(line) {
+                board.push((i, line));
+                continue;
+            }
+            if let Some((key, value)) = split_header(line) {
+                check_header(key, value).map_err(|e| kifu_error(i, e))?;
+                headers.push((key.to_string(), value.to_string()));
+                continue;
+            }
+            let record = record_from_headers(std::mem::take(&mut headers), &board, i)?;
+            builder = Some(RecordBuilder::new(record));
+        }
+        let builder = builder.as_mut().unwrap();
+
+        if line.starts_with("手数") {
+            continue;
+        } else if let Some(comment) = line.strip_prefix('*') {
+            builder.comment(comment.to_string());
+        } else if let Some(ply) = parse_variation(line) {
+            builder.start_variation(ply).map_err(|e| kifu_error(i, e))?;
+        } else {
+            parse_move_line(builder, line).map_err(|e| kifu_error(i, e))?;
+        }
+    }
+
+    match builder {
+        Some(builder) => Ok(builder.finish()),
+        None => record_from_headers(headers, &board, kif.lines().count()),
+    }
+}
+
+fn parse_move_line(builder: &mut RecordBuilder, line: &str) -> Result<(), String> {
+    let mut tokens = line.split_ascii_whitespace();
+    let ply = tokens
+        .next()
+        .and_then(|t| t.parse::<usize>().ok())
+        .ok_or_else(|| format!("invalid line {}", line))?;
+    let text = tokens
+        .next()
+        .ok_or_else(|| format!("no move in {}", line))?;
+    let rest = tokens.collect::<Vec<_>>().join("");
+    if ply != builder.next_ply() {
+        return Err(format!(
+            "expected move {} but got {}",
+            builder.next_ply(),
+            ply
+        ));
+    }
+
+    if let Some(result) = japanese::parse_result_name(text) {
+        if builder.is_main_line() {
+            builder.record.result = Some(result);
+        }
+        return Ok(());
+    }
+    let hand = japanese::parse_kif_move(text, builder.last)?;
+    let mut mv = MoveRecord::new(hand);
+    mv.time = parse_time(&rest);
+    builder.push(mv)
+}
+
+/// Parse the time of a move like `( 0:01/00:00:01)`
+fn parse_time(s: &str) -> Option<Duration> {
+    let s = s.trim_start_matches('(');
+    let (per_move, _) = s.split_once('/')?;
+    let (minutes, seconds) = per_move.split_once(':')?;
+    let seconds = minutes.trim().parse::<u64>().ok()? * 60 + seconds.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+fn format_time(time: Duration, total: Duration) -> String {
+    let (t, total) = (super::whole_seconds(time), super::whole_seconds(total));
+    format!(
+        "({:>2}:{:02}/{:02}:{:02}:{:02})",
+        t / 60,
+        t % 60,
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+/// Pad given move to the width of 14, counting zenkaku characters as 2
+fn pad(s: &str) -> String {
+    let width = s
+        .chars()
+        .map(|c| if c.is_ascii() { 1 } else { 2 })
+        .sum::<usize>();
+    format!("{}{}", s, " ".repeat(14usize.saturating_sub(width)))
+}
+
+fn write_line(moves: &[MoveRecord], ply: usize, start: &LineStart, out: &mut String) {
+    let mut state = start.clone();
+    for (i, mv) in moves.iter().enumerate() {
+        let text = japanese::format_kif_move(&state.ban, &mv.hand, state.last);
+        let next = match state.next(mv) {
+            Some(next) => next,
+            None => break,
+        };
+        let time = match mv.time {
+            Some(time) => format_time(time, next.times[state.ban.turn as usize]),
+            None => String::new(),
+        };
+        let variation = if mv.variations.is_empty() { "" } else { "+" };
+        let line = format!("{:>4} {} {}{}", ply + i, pad(&text), time, variation);
+        out.push_str(line.trim_end());
+        out.push('\n');
+        for comment in &mv.comments {
+            out.push_str(&format!("*{}\n", comment));
+        }
+        state = next;
+    }
+}
+
+/// Write given record in KIF
+pub fn write(record: &GameRecord) -> String {
+    let mut out = String::from("#KIF version=2.0 encoding=UTF-8\n");
+    write_headers(record, &mut out);
+    out.push_str("手数----指手---------消費時間--\n");
+
+    let start = LineStart::new(&record.initial);
+    write_line(&record.moves, first_ply(record), &start, &mut out);
+    if let Some(result) = record.result {
+        out.push_str(&format!(
+            "{:>4} {}\n",
+            first_ply(record) + record.moves.len(),
+            japanese::result_name(result)
+        ));
+        write_summary(record, &mut out);
+    }
+    write_variations(
+        &record.moves,
+        first_ply(record),
+        &start,
+        &mut out,
+        &mut write_line,
+    );
+    out
+}
+
+#[test]
+fn kif_round_trip() {
+    let kif = "#KIF version=2.0 encoding=UTF-8
+手合割：平手
+先手：Alice
+後手：Bob
+*opening comment
+手数----指手---------消費時間--
+   1 ７六歩(77)     ( 0:10/00:00:10)
+*good move
+   2 ３四歩(33)     ( 0:05/00:00:05)
+   3 ２二角成(88)   ( 1:00/00:01:10)+
+   4 同　銀(31)     ( 0:03/00:00:08)
+   5 ４五角打       ( 0:01/00:01:11)
+   6 投了
+まで5手で先手の勝ち
+
+変化：3手
+   3 ６八銀(79)     ( 0:01/00:00:11)
+   4 ８四歩(83)     ( 0:01/00:00:06)+
+
+変化：4手
+   4 ８八角成(22)   ( 0:02/00:00:07)
+";
+    let record = parse(kif).unwrap();
+    assert_eq!(record.header("先手"), Some("Alice"));
+    assert_eq!(record.comments, vec!["opening comment".to_string()]);
+    assert_eq!(record.moves.len(), 5);
+    assert_eq!(record.moves[0].comments, vec!["good move".to_string()]);
+    assert_eq!(record.moves[2].time, Some(Duration::from_secs(60)));
+    assert_eq!(record.moves[2].variations.len(), 1);
+    assert_eq!(record.moves[2].variations[0][1].variations.len(), 1);
+    assert_eq!(record.result, Some(crate::record::GameResult::Resign));
+    assert_eq!(
+        record.last_ban().unwrap().to_sfen(),
+        "lnsgkg1nl/1r5s1/pppppp1pp/6p2/5B3/2P6/PP1PPPPPP/7R1/LNSGKGSNL w b 6"
+    );
+    assert_eq!(write(&record), kif);
+}
+
+#[test]
+fn kif_errors() {
+    let error = |kif: &str| match parse(kif) {
+        Err(Error::Kifu(e)) => e.line,
+        _ => panic!("{}", kif),
+    };
+    assert_eq!(error("手合割：十一枚落ち\n"), 1);
+    assert_eq!(error("手合割：その他\n手数----\n"), 2);
+    assert_eq!(error("手数----\n   1 ７六歩(77)\n   3 ３四歩(33)\n"), 3);
+    assert_eq!(error("手数----\n   1 ７五歩(77)\n"), 2);
+    assert_eq!(error("手数----\n   1 同　歩(77)\n"), 2);
+    assert_eq!(error("手数----\n変化：1手\n"), 2);
+}
+
+#[test]
+fn kif_bod() {
+    let kif = "#KIF version=2.0 encoding=UTF-8
+作者：Alice
+後手の持駒：飛二　角　金三　銀四　桂三　香三　歩十六　
+  ９ ８ ７ ６ ５ ４ ３ ２ １
++---------------------------+
+| ・ ・ ・ ・ ・ ・ ・v桂v香|一
+| ・ ・ ・ ・ ・ ・ ・v玉 ・|二
+| ・ ・ ・ ・ ・ ・ ・v歩 ・|三
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|四
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|五
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|六
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|七
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|八
+| ・ ・ ・ ・ ・ ・ ・ ・ ・|九
++---------------------------+
+先手の持駒：角　歩　
+先手番
+手数----指手---------消費時間--
+   1 ２四歩打
+   2 同　歩(23)
+   3 ３三角打
+   4 ３一玉(22)
+   5 ２二角成(33)
+";
+    let record = parse(kif).unwrap();
+    assert_eq!(
+        record.initial.to_sfen(),
+        "7nl/7k1/7p1/9/9/9/9/9/9 b BP2rb3g4s3n3l16p 1"
+    );
+    assert_eq!(record.moves.len(), 5);
+    assert_eq!(write(&record), kif);
+}
+
+#[test]
+fn kif_after_move_count() {
+    use crate::{ban2::Ban2 as Ban, record::GameResult};
+    use std::convert::TryInto;
+
+    let initial = Ban::from_sfen("7nl/7k1/7p1/9/9/9/9/9/9 b BP2rb3g4s3n3l16p 31").unwrap();
+    let mut record = GameRecord::new(initial);
+    for hand in ["P*2d", "2c2d", "B*3c"].iter() {
+        record
+            .push(MoveRecord::new((*hand).try_into().unwrap()))
+            .unwrap();
+    }
+    record.moves[1].variations = vec![vec![MoveRecord::new("2b3b".try_into().unwrap())]];
+    record.result = Some(GameResult::Resign);
+
+    let kif = write(&record);
+    assert!(kif.contains("\n手数＝30\n"), "{}", kif);
+    assert!(kif.contains("\n  31 ２四歩打\n"), "{}", kif);
+    assert!(
+        kif.contains("\n  34 投了\nまで33手で先手の勝ち\n"),
+        "{}",
+        kif
+    );
+    assert!(kif.contains("\n変化：32手\n  32 ３二玉(22)\n"), "{}", kif);
+    assert_eq!(parse(&kif), Ok(record.clone()));
+    assert_eq!(super::ki2::parse(&super::ki2::write(&record)), Ok(record));
+
+    // moves are numbered after the move count of the board
+    let error = |kif: &str| match parse(kif) {
+        Err(Error::Kifu(e)) => e.message,
+        r => panic!("{:?}", r),
+    };
+    let from_one = kif.replace("  31 ２四歩打", "   1 ２四歩打");
+    assert_eq!(error(&from_one), "expected move 31 but got 1");
+}
@@ -1,11 +1,25 @@
 #![allow(clippy::needless_range_loop)]
-#![allow(clippy::result_unit_err)]
 #![feature(bindings_after_at)]
 
 pub mod ban2;
+pub mod book;
+pub mod csa_client;
+pub mod elo;
+pub mod error;
 pub mod evaluator;
+pub mod handicap;
+pub mod kifu;
+pub mod mate;
+pub mod notation;
+pub mod record;
+pub mod rules;
+pub mod time_manager;
+pub mod usi;
+pub mod usi_engine;
 
 use std::convert::{TryFrom, TryInto};
+
+use error::Error;
 /*
 (0-indexed)
 x <-----------
@@ -20,7 +34,8 @@ x <-----------
 
 pub const START_POS: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OnBoardPiece {
     piece: Piece,
     promoted: bool,
@@ -40,6 +55,7 @@ impl From<&OnBoardPiece> for String {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     Fu,
     Ou,
@@ -201,7 +217,7 @@ impl Piece {
 }
 
 impl TryFrom<char> for Piece {
-    type Error = ();
+    type Error = Error;
 
     fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
@@ -213,7 +229,7 @@ impl TryFrom<char> for Piece {
             'N' | 'n' => Ok(Piece::Keima),
             'L' | 'l' => Ok(Piece::Kyosha),
             'P' | 'p' => Ok(Piece::Fu),
-            _ => Err(()),
+            _ => Err(Error::InvalidPiece(c)),
         }
     }
 }
@@ -243,7 +259,7 @@ impl From<PieceBoolPair> for char {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Hand {
     Movement {
         x: usize,
@@ -259,17 +275,39 @@ pub enum Hand {
     },
 }
 
+impl Hand {
+    /// Get the position where the piece is moved or put
+    pub fn destination(&self) -> (usize, usize) {
+        match *self {
+            Hand::Movement { x, y, dx, dy, .. } => {
+                ((x as isize + dx) as usize, (y as isize + dy) as usize)
+            }
+            Hand::Putting { x, y, .. } => (x, y),
+        }
+    }
+}
+
 impl TryFrom<&str> for Hand {
-    type Error = ();
+    type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let cc = value.chars().collect::<Vec<_>>();
-        if cc[0].is_digit(10) {
-            let x = cc[0] as usize - '0' as usize;
-            let y = cc[1] as usize - 'a' as usize + 1;
-            let ax = cc[2] as usize - '0' as usize;
-            let ay = cc[3] as usize - 'a' as usize + 1;
-            let with_promote = cc.len() == 5 && cc[4] == '+';
+        let invalid = || Error::InvalidHand(value.to_string());
+        let position = |x: char, y: char| {
+            if ('1'..='9').contains(&x) && ('a'..='i').contains(&y) {
+                Ok((x as usize - '0' as usize, y as usize - 'a' as usize + 1))
+            } else {
+                Err(invalid())
+            }
+        };
+        if cc.len() == 4 && cc[1] == '*' {
+            let piece = cc[0].try_into()?;
+            let (x, y) = position(cc[2], cc[3])?;
+            Ok(Hand::Putting { piece, x, y })
+        } else if cc.len() == 4 || cc.len() == 5 && cc[4] == '+' {
+            let (x, y) = position(cc[0], cc[1])?;
+            let (ax, ay) = position(cc[2], cc[3])?;
+            let with_promote = cc.len() == 5;
 
             Ok(Hand::Movement {
                 x,
@@ -278,17 +316,28 @@ impl TryFrom<&str> for Hand {
                 dy: ay as isize - y as isize,
                 with_promote,
             })
-        } else if cc.len() != 4 || !cc[0].is_ascii_alphabetic() || cc[1] != '*' {
-            Err(())
         } else {
-            let piece = cc[0].try_into()?;
-            let x = cc[2] as usize - '0' as usize;
-            let y = cc[3] as usize - 'a' as usize + 1;
-            Ok(Hand::Putting { piece, x, y })
+            Err(invalid())
         }
     }
 }
 
+/// Serialized as a usi move like `7g7f`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hand {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from(self.clone()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hand {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let usi = String::deserialize(deserializer)?;
+        usi.as_str().try_into().map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<Hand> for String {
     fn from(h: Hand) -> Self {
         let mut mv = String::new();
@@ -0,0 +1,141 @@
+use crate::{ban2::Ban2 as Ban, evaluator, Hand};
+
+/// Find a move which checkmates the opponent immediately.
+/// Putting Fu is never returned since uchifuzume is rejected by `Ban2::put_piece`.
+pub fn mate_in_one(ban: &Ban) -> Option<Hand> {
+    ban.get_check_bans()
+        .into_iter()
+        .find(|(next, _)| next.get_evasion_bans().is_empty())
+        .map(|(_, hand)| hand)
+}
+
+/// Result of `solve_mate`
+#[derive(Debug, Clone)]
+pub enum MateResult {
+    /// Checkmate sequence, the defender taking the longest way
+    Mate(Vec<Hand>),
+    /// No checkmate within given depth
+    NoMate,
+    /// Gave up due to the node limit, or stopped by `STOP` or `DEADLINE` of the evaluator
+    Unknown,
+}
+
+struct MateSearch {
+    nodes: usize,
+    max_nodes: usize,
+}
+
+/// Search a checkmate sequence by checks only, up to `max_depth` plies.
+/// Gives up with `MateResult::Unknown` when more than `max_nodes` positions are visited, or
+/// when it is stopped by `STOP` or `DEADLINE` of the evaluator.
+pub fn solve_mate(ban: &Ban, max_depth: usize, max_nodes: usize) -> MateResult {
+    let mut search = MateSearch {
+        nodes: 0,
+        max_nodes,
+    };
+    // deepen at the root only, so that quick mates are found without searching deep
+    for depth in (1..=max_depth).step_by(2) {
+        match search.attack(ban, depth) {
+            Some(Some(hands)) => return MateResult::Mate(hands),
+            Some(None) => {}
+            None => return MateResult::Unknown,
+        }
+    }
+    MateResult::NoMate
+}
+
+impl MateSearch {
+    /// Count a visited node. `None` means the search gives up
+    fn visit(&mut self) -> Option<()> {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes || evaluator::should_stop(self.nodes as i32) {
+            None
+        } else {
+            Some(())
+        }
+    }
+
+    /// Find the shortest mate within `depth` plies. Outer `None` means the search gave up
+    fn attack(&mut self, ban: &Ban, depth: usize) -> Option<Option<Vec<Hand>>> {
+        self.visit()?;
+        if let Some(hand) = mate_in_one(ban) {
+            return Some(Some(vec![hand]));
+        }
+        // after a mate is found, only shorter ones are searched
+        let mut depth = depth;
+        let mut shortest = None;
+        for (next, hand) in ban.get_check_bans() {
+            if depth < 3 {
+                break;
+            }
+            if let Some(mut hands) = self.defend(&next, depth - 1)? {
+                hands.insert(0, hand);
+                depth = hands.len() - 2;
+                shortest = Some(hands);
+            }
+        }
+        Some(shortest)
+    }
+
+    /// Find the longest defence against mates within `depth` plies
+    fn defend(&mut self, ban: &Ban, depth: usize) -> Option<Option<Vec<Hand>>> {
+        self.visit()?;
+        let mut longest: Option<Vec<Hand>> = None;
+        for (next, hand) in ban.get_evasion_bans() {
+            match self.attack(&next, depth - 1)? {
+                Some(mut hands) => {
+                    hands.insert(0, hand);
+                    if longest.as_ref().is_none_or(|l| l.len() < hands.len()) {
+                        longest = Some(hands);
+                    }
+                }
+                None => return Some(None),
+            }
+        }
+        Some(Some(longest.unwrap_or_default()))
+    }
+}
+
+#[test]
+fn mate_in_one_test() {
+    let ban = Ban::from_sfen("7nk/7s1/9/9/9/9/9/9/K7L b G 1").unwrap();
+    assert_eq!(
+        mate_in_one(&ban).map(String::from),
+        Some("G*1b".to_string())
+    );
+
+    // P*1b is uchifuzume
+    let mut ban = Ban::from_sfen("7nk/7s1/9/9/9/9/9/9/K7L b P 1").unwrap();
+    assert!(mate_in_one(&ban).is_none());
+    assert_eq!(
+        ban.put_piece(crate::Piece::Fu, 1, 2),
+        Err(crate::error::Error::IllegalMove(
+            crate::error::IllegalMoveKind::Uchifuzume
+        ))
+    );
+    assert_eq!(ban.to_sfen(), "7nk/7s1/9/9/9/9/9/9/K7L b P 1");
+
+    let ban = Ban::new();
+    assert!(mate_in_one(&ban).is_none());
+}
+
+#[test]
+fn solve_mate_test() {
+    let ban = Ban::from_sfen("7gk/9/7+R1/9/9/9/9/9/K8 b G 1").unwrap();
+    match solve_mate(&ban, 7, 100_000) {
+        MateResult::Mate(hands) => assert_eq!(
+            hands.into_iter().map(String::from).collect::<Vec<_>>(),
+            vec!["2c1c", "2a1b", "G*2b"]
+        ),
+        r => panic!("{:?}", r),
+    }
+
+    let ban = Ban::from_sfen("7nk/7s1/9/9/9/9/9/9/K7L b G 1").unwrap();
+    match solve_mate(&ban, 7, 100_000) {
+        MateResult::Mate(hands) => assert_eq!(hands.len(), 1),
+        r => panic!("{:?}", r),
+    }
+
+    let ban = Ban::from_sfen("8k/9/9/9/9/9/9/9/K8 b P 1").unwrap();
+    assert!(matches!(solve_mate(&ban, 5, 100_000), MateResult::NoMate));
+}
@@ -0,0 +1,4 @@
+//! Human readable notations of moves
+
+pub mod japanese;
+pub mod western;
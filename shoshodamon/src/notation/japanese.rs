@@ -0,0 +1,467 @@
+//! Japanese notation of moves like `▲７六歩` or `△同　銀`, also used by KIF and KI2
+
+use crate::{ban2::Ban2 as Ban, error::Error, record::GameResult, Hand, Piece};
+
+pub(crate) const ZENKAKU_DIGITS: [char; 9] = ['１', '２', '３', '４', '５', '６', '７', '８', '９'];
+pub(crate) const KANJI_DIGITS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// Names of pieces, the first one of the same piece is used for writing
+const PIECE_NAMES: [(&str, Piece, bool); 19] = [
+    ("歩", Piece::Fu, false),
+    ("香", Piece::Kyosha, false),
+    ("桂", Piece::Keima, false),
+    ("銀", Piece::Gin, false),
+    ("金", Piece::Kin, false),
+    ("角", Piece::Kaku, false),
+    ("飛", Piece::Hisha, false),
+    ("玉", Piece::Ou, false),
+    ("王", Piece::Ou, false),
+    ("と", Piece::Fu, true),
+    ("成香", Piece::Kyosha, true),
+    ("杏", Piece::Kyosha, true),
+    ("成桂", Piece::Keima, true),
+    ("圭", Piece::Keima, true),
+    ("成銀", Piece::Gin, true),
+    ("全", Piece::Gin, true),
+    ("馬", Piece::Kaku, true),
+    ("龍", Piece::Hisha, true),
+    ("竜", Piece::Hisha, true),
+];
+
+/// Names of results written as moves in KIF
+const RESULT_NAMES: [(&str, GameResult); 9] = [
+    ("投了", GameResult::Resign),
+    ("詰み", GameResult::Checkmate),
+    ("千日手", GameResult::Sennichite),
+    ("持将棋", GameResult::Jishogi),
+    ("切れ負け", GameResult::Timeout),
+    ("入玉勝ち", GameResult::Declaration),
+    ("反則負け", GameResult::IllegalMove),
+    ("反則勝ち", GameResult::IllegalWin),
+    ("中断", GameResult::Interrupted),
+];
+
+pub(crate) fn piece_name(piece: Piece, promoted: bool) -> &'static str {
+    PIECE_NAMES
+        .iter()
+        .find(|(_, p, pr)| *p == piece && *pr == promoted)
+        .map(|(name, _, _)| *name)
+        .unwrap_or("")
+}
+
+/// Parse a piece name at the start of given string, returning the rest
+pub(crate) fn parse_piece_name(s: &str) -> Option<(Piece, bool, &str)> {
+    PIECE_NAMES
+        .iter()
+        .find(|(name, _, _)| s.starts_with(name))
+        .map(|(name, piece, promoted)| (*piece, *promoted, &s[name.len()..]))
+}
+
+pub(crate) fn result_name(result: GameResult) -> &'static str {
+    RESULT_NAMES
+        .iter()
+        .find(|(_, r)| *r == result)
+        .map(|(name, _)| *name)
+        .unwrap()
+}
+
+/// Parse a result name at the start of given string
+pub(crate) fn parse_result_name(s: &str) -> Option<GameResult> {
+    RESULT_NAMES
+        .iter()
+        .find(|(name, _)| s.starts_with(name))
+        .map(|(_, r)| *r)
+}
+
+/// Write the destination like `７六`, or `同　` when it is same as the last destination
+pub(crate) fn format_destination((x, y): (usize, usize), last: Option<(usize, usize)>) -> String {
+    if last == Some((x, y)) {
+        "同　".to_string()
+    } else {
+        format!("{}{}", ZENKAKU_DIGITS[x - 1], KANJI_DIGITS[y - 1])
+    }
+}
+
+/// Parse the destination at the start of given string, returning the rest
+pub(crate) fn parse_destination(
+    s: &str,
+    last: Option<(usize, usize)>,
+) -> Option<((usize, usize), &str)> {
+    if let Some(rest) = s.strip_prefix('同') {
+        return Some((last?, rest.trim_start_matches(['　', ' '])));
+    }
+    let mut chars = s.chars();
+    let x = chars.next()?;
+    let x = ZENKAKU_DIGITS
+        .iter()
+        .position(|c| *c == x)
+        .or_else(|| x.to_digit(10).filter(|d| *d > 0).map(|d| d as usize - 1))?;
+    let y = chars.next()?;
+    let y = KANJI_DIGITS.iter().position(|c| *c == y)?;
+    Some(((x + 1, y + 1), chars.as_str()))
+}
+
+/// Check the moving piece could promote but the move does not promote
+pub(crate) fn is_narazu(ban: &Ban, hand: &Hand) -> bool {
+    match *hand {
+        Hand::Movement {
+            x,
+            y,
+            with_promote: false,
+            ..
+        } => match ban.get_position(x, y) {
+            Some(p) if !p.promoted && !matches!(p.piece, Piece::Kin | Piece::Ou) => {
+                let (_, ay) = hand.destination();
+                let in_zone = |y: usize| ban.turn && y <= 3 || !ban.turn && y >= 7;
+                in_zone(y) || in_zone(ay)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Get the piece moved or put by given hand
+pub(crate) fn moving_piece(ban: &Ban, hand: &Hand) -> Option<(Piece, bool)> {
+    match *hand {
+        Hand::Movement { x, y, .. } => ban.get_position(x, y).map(|p| (p.piece, p.promoted)),
+        Hand::Putting { piece, .. } => Some((piece, false)),
+    }
+}
+
+/// Write a move in KIF like `７六歩(77)`, `同　角成(88)` or `５五角打`
+pub(crate) fn format_kif_move(ban: &Ban, hand: &Hand, last: Option<(usize, usize)>) -> String {
+    let (piece, promoted) = moving_piece(ban, hand).unwrap_or((Piece::Fu, false));
+    let mut s = format_destination(hand.destination(), last);
+    s.push_str(piece_name(piece, promoted));
+    match *hand {
+        Hand::Movement {
+            x, y, with_promote, ..
+        } => {
+            if with_promote {
+                s.push('成');
+            } else if is_narazu(ban, hand) {
+                s.push_str("不成");
+            }
+            s.push_str(&format!("({}{})", x, y));
+        }
+        Hand::Putting { .. } => s.push('打'),
+    }
+    s
+}
+
+/// Parse a move in KIF
+pub(crate) fn parse_kif_move(s: &str, last: Option<(usize, usize)>) -> Result<Hand, String> {
+    let invalid = || format!("invalid move {}", s);
+    let ((ax, ay), rest) = parse_destination(s, last).ok_or_else(invalid)?;
+    let (piece, _, rest) = parse_piece_name(rest).ok_or_else(invalid)?;
+    let (with_promote, rest) = if let Some(rest) = rest.strip_prefix("不成") {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix('成') {
+        (true, rest)
+    } else {
+        (false, rest)
+    };
+    if rest.starts_with('打') {
+        return Ok(Hand::Putting {
+            piece,
+            x: ax,
+            y: ay,
+        });
+    }
+    let origin = rest
+        .strip_prefix('(')
+        .and_then(|r| r.get(..2))
+        .map(|r| r.chars().filter_map(|c| c.to_digit(10)).collect::<Vec<_>>())
+        .filter(|d| d.len() == 2 && d.iter().all(|d| (1..=9).contains(d)))
+        .ok_or_else(invalid)?;
+    let (x, y) = (origin[0] as usize, origin[1] as usize);
+    Ok(Hand::Movement {
+        x,
+        y,
+        dx: ax as isize - x as isize,
+        dy: ay as isize - y as isize,
+        with_promote,
+    })
+}
+
+/// Get origins of the pieces same as the moving one which can move to the destination
+fn same_piece_origins(
+    ban: &Ban,
+    piece: (Piece, bool),
+    dest: (usize, usize),
+) -> Vec<(usize, usize)> {
+    let mut origins = Vec::new();
+    for (_, hand) in ban.get_possibility_bans(ban.turn) {
+        if let Hand::Movement { x, y, .. } = hand {
+            if hand.destination() == dest
+                && moving_piece(ban, &hand) == Some(piece)
+                && !origins.contains(&(x, y))
+            {
+                origins.push((x, y));
+            }
+        }
+    }
+    origins
+}
+
+/// Get the relative notation (右, 左, 直, 上, 引, 寄 and 打) which tells the moving piece from
+/// the others of same kind
+pub(crate) fn relative(ban: &Ban, hand: &Hand) -> String {
+    let piece = match moving_piece(ban, hand) {
+        Some(piece) => piece,
+        None => return String::new(),
+    };
+    let dest = hand.destination();
+    let origins = same_piece_origins(ban, piece, dest);
+    let (x, y) = match *hand {
+        Hand::Putting { .. } if origins.is_empty() => return String::new(),
+        Hand::Putting { .. } => return "打".to_string(),
+        Hand::Movement { x, y, .. } => (x, y),
+    };
+    let others = origins
+        .into_iter()
+        .filter(|o| *o != (x, y))
+        .collect::<Vec<_>>();
+    if others.is_empty() {
+        return String::new();
+    }
+
+    // seen from the moving side
+    let forward = |(_, oy): (usize, usize)| {
+        let dy = dest.1 as isize - oy as isize;
+        if ban.turn {
+            -dy
+        } else {
+            dy
+        }
+    };
+    let right = |(ox, _): (usize, usize)| {
+        if ban.turn {
+            -(ox as isize)
+        } else {
+            ox as isize
+        }
+    };
+    let direction = |o: (usize, usize)| match forward(o) {
+        f if f > 0 => "上",
+        f if f < 0 => "引",
+        _ => "寄",
+    };
+
+    let my_direction = direction((x, y));
+    let same_direction = others
+        .iter()
+        .filter(|o| direction(**o) == my_direction)
+        .copied()
+        .collect::<Vec<_>>();
+    if same_direction.is_empty() {
+        return my_direction.to_string();
+    }
+    let is_big = matches!(piece, (Piece::Hisha, true) | (Piece::Kaku, true));
+    if !is_big && x == dest.0 && forward((x, y)) > 0 {
+        return "直".to_string();
+    }
+
+    let lateral = |group: &[(usize, usize)]| {
+        if group.iter().all(|o| right(*o) < right((x, y))) {
+            Some("右")
+        } else if group.iter().all(|o| right(*o) > right((x, y))) {
+            Some("左")
+        } else {
+            None
+        }
+    };
+    match (lateral(&others), lateral(&same_direction)) {
+        (Some(l), _) => l.to_string(),
+        (None, Some(l)) => format!("{}{}", l, my_direction),
+        (None, None) => my_direction.to_string(),
+    }
+}
+
+/// Write a move in KI2 without the turn mark, like `７六歩`, `同　銀不成` or `５八金右`
+pub(crate) fn format_ki2_move(ban: &Ban, hand: &Hand, last: Option<(usize, usize)>) -> String {
+    let (piece, promoted) = moving_piece(ban, hand).unwrap_or((Piece::Fu, false));
+    let mut s = format_destination(hand.destination(), last);
+    s.push_str(piece_name(piece, promoted));
+    s.push_str(&relative(ban, hand));
+    if let Hand::Movement { with_promote, .. } = hand {
+        if *with_promote {
+            s.push('成');
+        } else if is_narazu(ban, hand) {
+            s.push_str("不成");
+        }
+    }
+    s
+}
+
+/// Parse a move in KI2 without the turn mark
+pub(crate) fn parse_ki2_move(
+    ban: &Ban,
+    s: &str,
+    last: Option<(usize, usize)>,
+) -> Result<Hand, String> {
+    let invalid = || format!("invalid move {}", s);
+    let (dest, rest) = parse_destination(s, last).ok_or_else(invalid)?;
+    let (piece, promoted, rest) = parse_piece_name(rest).ok_or_else(invalid)?;
+    let relative_part = rest
+        .chars()
+        .take_while(|c| "右左直上引寄".contains(*c))
+        .collect::<String>();
+    let rest = &rest[relative_part.len()..];
+    let (with_promote, is_putting) = match rest {
+        "成" => (true, false),
+        "打" => (false, true),
+        "" | "不成" | "生" => (false, false),
+        _ => return Err(invalid()),
+    };
+
+    let candidates = ban
+        .get_possibility_bans(ban.turn)
+        .into_iter()
+        .map(|(_, hand)| hand)
+        .filter(|hand| {
+            hand.destination() == dest
+                && moving_piece(ban, hand) == Some((piece, promoted))
+                && match hand {
+                    Hand::Movement {
+                        with_promote: p, ..
+                    } => !is_putting && *p == with_promote,
+                    Hand::Putting { .. } => !with_promote,
+                }
+        })
+        .collect::<Vec<_>>();
+
+    let expected = format!("{}{}", relative_part, if is_putting { "打" } else { "" });
+    let mut matched = candidates
+        .iter()
+        .filter(|hand| relative(ban, hand) == expected)
+        .collect::<Vec<_>>();
+    if matched.is_empty() && candidates.len() == 1 {
+        // be lenient to redundant or omitted relative notations
+        matched.push(&candidates[0]);
+    }
+    match matched.as_slice() {
+        [hand] => Ok((*hand).clone()),
+        [] => Err(format!("no legal move for {}", s)),
+        _ => Err(format!("ambiguous move {}", s)),
+    }
+}
+
+/// Write a move with the turn mark like `▲７六歩`, `△同　銀`, `▲５五角打` or `▲５八金右`.
+/// `previous` is the move played just before, which makes `同` for recaptures
+pub fn format_move(ban: &Ban, hand: &Hand, previous: Option<&Hand>) -> String {
+    let mark = if ban.turn { '▲' } else { '△' };
+    let last = previous.map(Hand::destination);
+    format!("{}{}", mark, format_ki2_move(ban, hand, last))
+}
+
+/// Parse a move written like `format_move`. The turn mark is optional and `☗`/`☖` are also
+/// accepted
+pub fn parse_move(ban: &Ban, s: &str, previous: Option<&Hand>) -> Result<Hand, Error> {
+    let s = s.trim();
+    let body = match s.chars().next() {
+        Some(mark @ '▲') | Some(mark @ '☗') | Some(mark @ '△') | Some(mark @ '☖') => {
+            if (mark == '▲' || mark == '☗') != ban.turn {
+                return Err(Error::Notation(format!("unexpected turn at {}", s)));
+            }
+            &s[mark.len_utf8()..]
+        }
+        _ => s,
+    };
+    parse_ki2_move(ban, body, previous.map(Hand::destination)).map_err(Error::Notation)
+}
+
+#[test]
+fn ki2_relative() {
+    use std::convert::TryInto;
+
+    let cases = [
+        // golds at 6i, 5i and 4i
+        ("4k4/9/9/9/9/9/9/9/3GGG1K1 b - 1", "5i5h", "５八金直"),
+        ("4k4/9/9/9/9/9/9/9/3GGG1K1 b - 1", "4i5h", "５八金右"),
+        ("4k4/9/9/9/9/9/9/9/3GGG1K1 b - 1", "6i5h", "５八金左"),
+        // golds at 6h and 4i
+        ("4k4/9/9/9/9/9/9/3G5/5G1K1 b - 1", "6h5h", "５八金寄"),
+        ("4k4/9/9/9/9/9/9/3G5/5G1K1 b - 1", "4i5h", "５八金上"),
+        // gote golds at 4a and 6a, seen from gote
+        ("1K1g1g3/9/9/9/9/9/9/9/4k4 w - 1", "4a5b", "５二金左"),
+        ("1K1g1g3/9/9/9/9/9/9/9/4k4 w - 1", "6a5b", "５二金右"),
+        // silvers at 4c and 6c going back to 5d
+        ("4k4/9/3S1S3/9/9/9/9/9/4K4 b - 1", "4c5d+", "５四銀右成"),
+        ("4k4/9/3S1S3/9/9/9/9/9/4K4 b - 1", "6c5d", "５四銀左不成"),
+        // dragons need 右 and 左 even when going straight
+        ("4k4/9/9/9/9/9/9/+R7+R/4K4 b - 1", "9h5h", "５八龍左"),
+        ("4k4/9/9/9/9/9/9/+R7+R/4K4 b - 1", "1h5h", "５八龍右"),
+        ("4k4/9/9/9/9/9/9/4S4/4K4 b S 1", "S*5g", "５七銀打"),
+        ("4k4/9/9/9/9/9/9/9/4K4 b S 1", "S*5g", "５七銀"),
+    ];
+    for (sfen, usi, expected) in cases.iter() {
+        let ban = Ban::from_sfen(sfen).unwrap();
+        let hand: Hand = (*usi).try_into().unwrap();
+        assert_eq!(format_ki2_move(&ban, &hand, None), *expected, "{}", usi);
+        assert_eq!(parse_ki2_move(&ban, expected, None), Ok(hand), "{}", usi);
+    }
+}
+
+#[test]
+fn kif_move() {
+    use std::convert::TryInto;
+
+    let ban = Ban::from_sfen("4k4/9/9/9/9/9/9/1B7/4K4 b - 1").unwrap();
+    let hand: Hand = "8h2b+".try_into().unwrap();
+    assert_eq!(format_kif_move(&ban, &hand, Some((2, 2))), "同　角成(88)");
+    assert_eq!(parse_kif_move("同　角成(88)", Some((2, 2))), Ok(hand));
+    assert!(parse_kif_move("同　角成(88)", None).is_err());
+    assert!(parse_kif_move("２二角成(8", None).is_err());
+}
+
+#[test]
+fn japanese_notation() {
+    use std::convert::TryInto;
+
+    let mut ban = Ban::new();
+    let mut previous: Option<Hand> = None;
+    let moves = [
+        ("7g7f", "▲７六歩"),
+        ("3c3d", "△３四歩"),
+        ("8h2b+", "▲２二角成"),
+        ("3a2b", "△同　銀"),
+        // 打 is written only when a piece on board could also move there
+        ("B*5e", "▲５五角"),
+        ("4a3b", "△３二金"),
+        ("5e2b+", "▲２二角成"),
+    ];
+    for (usi, expected) in moves.iter() {
+        let hand: Hand = (*usi).try_into().unwrap();
+        assert_eq!(format_move(&ban, &hand, previous.as_ref()), *expected);
+        assert_eq!(
+            parse_move(&ban, expected, previous.as_ref()),
+            Ok(hand.clone())
+        );
+        ban.apply_hand(&hand).unwrap();
+        previous = Some(hand);
+    }
+
+    let ban = Ban::new();
+    assert_eq!(
+        parse_move(&ban, "☗７六歩", None),
+        parse_move(&ban, "７六歩", None)
+    );
+    assert!(matches!(
+        parse_move(&ban, "△７六歩", None),
+        Err(Error::Notation(_))
+    ));
+    assert!(matches!(
+        parse_move(&ban, "▲同　歩", None),
+        Err(Error::Notation(_))
+    ));
+    assert!(matches!(
+        parse_move(&ban, "▲７五歩", None),
+        Err(Error::Notation(_))
+    ));
+    assert!(matches!(
+        parse_move(&ban, "", None),
+        Err(Error::Notation(_))
+    ));
+}
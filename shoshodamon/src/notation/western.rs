@@ -0,0 +1,186 @@
+//! Western notation of moves by Hodges, like `P-7f`, `Bx2b+`, `S*5e` or `G6h-5h`
+
+use super::japanese::{is_narazu, moving_piece};
+use crate::{ban2::Ban2 as Ban, error::Error, Hand, Piece};
+
+/// Letters of pieces, which are prefixed with `+` when promoted
+const PIECE_LETTERS: [(char, Piece); 8] = [
+    ('P', Piece::Fu),
+    ('L', Piece::Kyosha),
+    ('N', Piece::Keima),
+    ('S', Piece::Gin),
+    ('G', Piece::Kin),
+    ('B', Piece::Kaku),
+    ('R', Piece::Hisha),
+    ('K', Piece::Ou),
+];
+
+pub(crate) fn piece_letter(piece: Piece) -> char {
+    PIECE_LETTERS
+        .iter()
+        .find(|(_, p)| *p == piece)
+        .map(|(c, _)| *c)
+        .unwrap()
+}
+
+fn format_square((x, y): (usize, usize)) -> String {
+    format!("{}{}", x, (b'a' + y as u8 - 1) as char)
+}
+
+/// Parse a square like `7f`, also accepting numeric ranks like `76`
+fn parse_square(s: &[char]) -> Option<(usize, usize)> {
+    let x = s.first()?.to_digit(10).filter(|x| *x != 0)? as usize;
+    let y = match *s.get(1)? {
+        c @ 'a'..='i' => c as usize - 'a' as usize + 1,
+        c @ '1'..='9' => c as usize - '0' as usize,
+        _ => return None,
+    };
+    Some((x, y))
+}
+
+/// Write a move like `P-7f`, `Bx2b+`, `S*5e` or `G6h-5h`. The origin is written only when
+/// another piece of the same kind can move to the destination
+pub fn format_move(ban: &Ban, hand: &Hand) -> String {
+    let (piece, promoted) = moving_piece(ban, hand).unwrap_or((Piece::Fu, false));
+    let dest = hand.destination();
+    let mut s = String::new();
+    if promoted {
+        s.push('+');
+    }
+    s.push(piece_letter(piece));
+
+    match *hand {
+        Hand::Putting { .. } => s.push('*'),
+        Hand::Movement {
+            x, y, with_promote, ..
+        } => {
+            let ambiguous =
+                ban.get_possibility_bans(ban.turn)
+                    .iter()
+                    .any(|(_, other)| match *other {
+                        Hand::Movement { x: ox, y: oy, .. } => {
+                            (ox, oy) != (x, y)
+                                && other.destination() == dest
+                                && moving_piece(ban, other) == Some((piece, promoted))
+                        }
+                        Hand::Putting { .. } => false,
+                    });
+            if ambiguous {
+                s.push_str(&format_square((x, y)));
+            }
+            s.push(if ban.get_position(dest.0, dest.1).is_some() {
+                'x'
+            } else {
+                '-'
+            });
+            s.push_str(&format_square(dest));
+            if with_promote {
+                s.push('+');
+            } else if is_narazu(ban, hand) {
+                s.push('=');
+            }
+            return s;
+        }
+    }
+    s.push_str(&format_square(dest));
+    s
+}
+
+/// Parse a move written like `format_move`
+pub fn parse_move(ban: &Ban, s: &str) -> Result<Hand, Error> {
+    let invalid = || Error::Notation(format!("invalid move {}", s));
+    let chars = s.trim().chars().collect::<Vec<_>>();
+    let (promoted, rest) = match chars.split_first() {
+        Some(('+', rest)) => (true, rest),
+        _ => (false, chars.as_slice()),
+    };
+    let (letter, rest) = rest.split_first().ok_or_else(invalid)?;
+    let piece = PIECE_LETTERS
+        .iter()
+        .find(|(c, _)| *c == letter.to_ascii_uppercase())
+        .map(|(_, p)| *p)
+        .ok_or_else(invalid)?;
+    let (origin, rest) = match parse_square(rest) {
+        Some(origin) => (Some(origin), &rest[2..]),
+        None => (None, rest),
+    };
+    let (separator, rest) = rest.split_first().ok_or_else(invalid)?;
+    let dest = parse_square(rest).ok_or_else(invalid)?;
+    let with_promote = match &rest[2..] {
+        [] | ['='] => false,
+        ['+'] => true,
+        _ => return Err(invalid()),
+    };
+    let capture = ban.get_position(dest.0, dest.1).is_some();
+    match separator {
+        '*' if origin.is_none() && !promoted && !with_promote => {}
+        '-' if !capture => {}
+        'x' if capture => {}
+        '-' | 'x' => return Err(Error::Notation(format!("wrong capture marker in {}", s))),
+        _ => return Err(invalid()),
+    }
+
+    let candidates = ban
+        .get_possibility_bans(ban.turn)
+        .into_iter()
+        .map(|(_, hand)| hand)
+        .filter(|hand| {
+            hand.destination() == dest
+                && moving_piece(ban, hand) == Some((piece, promoted))
+                && match *hand {
+                    Hand::Putting { .. } => *separator == '*',
+                    Hand::Movement {
+                        x,
+                        y,
+                        with_promote: p,
+                        ..
+                    } => {
+                        *separator != '*' && p == with_promote && origin.is_none_or(|o| o == (x, y))
+                    }
+                }
+        })
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [hand] => Ok(hand.clone()),
+        [] => Err(Error::Notation(format!("no legal move for {}", s))),
+        _ => Err(Error::Notation(format!("ambiguous move {}", s))),
+    }
+}
+
+#[test]
+fn western_notation() {
+    use std::convert::TryInto;
+
+    let mut ban = Ban::new();
+    let moves = [
+        ("7g7f", "P-7f"),
+        ("3c3d", "P-3d"),
+        ("8h2b+", "Bx2b+"),
+        ("3a2b", "Sx2b"),
+        ("B*4e", "B*4e"),
+        ("4a3b", "G-3b"),
+        ("4e6c=", "Bx6c="),
+    ];
+    for (usi, expected) in moves.iter() {
+        let hand: Hand = (*usi).replace('=', "").as_str().try_into().unwrap();
+        assert_eq!(format_move(&ban, &hand), *expected);
+        assert_eq!(parse_move(&ban, expected), Ok(hand.clone()));
+        ban.apply_hand(&hand).unwrap();
+    }
+
+    // golds at 6i and 4i
+    let ban = Ban::from_sfen("4k4/9/9/9/9/9/9/9/3G1G2K b - 1").unwrap();
+    let hand: Hand = "6i5h".try_into().unwrap();
+    assert_eq!(format_move(&ban, &hand), "G6i-5h");
+    assert_eq!(parse_move(&ban, "G69-58"), Ok(hand));
+    assert!(matches!(parse_move(&ban, "G-5h"), Err(Error::Notation(_))));
+
+    let ban = Ban::new();
+    assert!(parse_move(&ban, "Px7f").is_err());
+    assert!(parse_move(&ban, "P-7f+").is_err());
+    assert!(parse_move(&ban, "P*7f").is_err());
+    assert!(parse_move(&ban, "Q-7f").is_err());
+    assert!(parse_move(&ban, "").is_err());
+    assert!(parse_move(&ban, "+").is_err());
+    assert_eq!(parse_move(&ban, "p-76"), parse_move(&ban, "P-7f"));
+}
@@ -0,0 +1,318 @@
+use std::{convert::TryInto, time::Duration};
+
+use crate::{ban2::Ban2 as Ban, error::Error, kifu::KifuFormat, Hand};
+
+/// Header keys of the metadata, same as KIF
+pub const SENTE_NAME: &str = "先手";
+pub const GOTE_NAME: &str = "後手";
+pub const EVENT: &str = "棋戦";
+pub const DATE: &str = "開始日時";
+
+/// A move of a game with its annotations
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRecord {
+    pub hand: Hand,
+    /// Time consumed for this move
+    pub time: Option<Duration>,
+    pub comments: Vec<String>,
+    /// Evaluation of the position after this move in centipawns, positive for sente
+    pub evaluation: Option<isize>,
+    /// Other lines which start from this move instead of `hand`
+    pub variations: Vec<Vec<MoveRecord>>,
+}
+
+impl MoveRecord {
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            time: None,
+            comments: Vec::new(),
+            evaluation: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// How the game ended. Each result is seen from the turn after the last move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    /// The turn resigned
+    Resign,
+    /// The turn is checkmated
+    Checkmate,
+    /// Draw by repetition
+    Sennichite,
+    /// Draw by impasse
+    Jishogi,
+    /// The turn lost on time
+    Timeout,
+    /// The turn declared its win by entering king
+    Declaration,
+    /// The turn lost by an illegal action
+    IllegalMove,
+    /// The last move was illegal, so the turn won
+    IllegalWin,
+    /// The game was stopped
+    Interrupted,
+}
+
+impl GameResult {
+    /// Get the winner, `Some(true)` for sente, when the game ended with `turn` to move
+    pub fn winner(&self, turn: bool) -> Option<bool> {
+        match self {
+            GameResult::Resign
+            | GameResult::Checkmate
+            | GameResult::Timeout
+            | GameResult::IllegalMove => Some(!turn),
+            GameResult::Declaration | GameResult::IllegalWin => Some(turn),
+            GameResult::Sennichite | GameResult::Jishogi | GameResult::Interrupted => None,
+        }
+    }
+}
+
+/// A whole game with its headers
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub initial: Ban,
+    pub moves: Vec<MoveRecord>,
+    /// Headers of kifu in its order, e.g. `("先手", "name")`
+    pub headers: Vec<(String, String)>,
+    /// Comments before the first move
+    pub comments: Vec<String>,
+    pub result: Option<GameResult>,
+}
+
+impl GameRecord {
+    pub fn new(initial: Ban) -> Self {
+        Self {
+            initial,
+            moves: Vec::new(),
+            headers: Vec::new(),
+            comments: Vec::new(),
+            result: None,
+        }
+    }
+
+    /// Get the value of given header
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of given header, keeping its place if it exists
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.headers.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.headers.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn sente_name(&self) -> Option<&str> {
+        self.header(SENTE_NAME)
+    }
+
+    pub fn gote_name(&self) -> Option<&str> {
+        self.header(GOTE_NAME)
+    }
+
+    pub fn event(&self) -> Option<&str> {
+        self.header(EVENT)
+    }
+
+    /// Get the date when the game started, as written in the kifu
+    pub fn date(&self) -> Option<&str> {
+        self.header(DATE)
+    }
+
+    /// Append a move to the main line, checking it is legal. The main line is replayed every
+    /// time, so long games should be built with a position kept alongside
+    pub fn push(&mut self, mv: MoveRecord) -> Result<(), Error> {
+        self.last_ban()?.apply_hand(&mv.hand)?;
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    /// Get the position after `ply` moves of the main line. `None` when the main line is shorter
+    /// or has an illegal move
+    pub fn ban_at(&self, ply: usize) -> Option<Ban> {
+        let mut ban = self.initial.clone();
+        for mv in self.moves.get(..ply)? {
+            ban.apply_hand(&mv.hand).ok()?;
+        }
+        Some(ban)
+    }
+
+    /// Get the position after all moves of the main line
+    pub fn last_ban(&self) -> Result<Ban, Error> {
+        let mut ban = self.initial.clone();
+        for mv in &self.moves {
+            ban.apply_hand(&mv.hand)?;
+        }
+        Ok(ban)
+    }
+
+    /// Get the turn to move after the main line, which the result is seen from
+    pub fn last_turn(&self) -> bool {
+        self.initial.turn ^ (self.moves.len() % 2 == 1)
+    }
+
+    /// Get the winner of the game, `Some(true)` for sente
+    pub fn winner(&self) -> Option<bool> {
+        self.result?.winner(self.last_turn())
+    }
+
+    pub fn parse(kifu: &str, format: KifuFormat) -> Result<Self, Error> {
+        format.parse(kifu)
+    }
+
+    pub fn to_kifu(&self, format: KifuFormat) -> String {
+        format.write(self)
+    }
+
+    /// Parse a USI position like `position startpos moves 7g7f 3c3d`, where `position` may be
+    /// omitted
+    pub fn from_usi(line: &str) -> Result<Self, Error> {
+        let tokens = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let tokens = match tokens.first() {
+            Some(&"position") => &tokens[1..],
+            _ => &tokens[..],
+        };
+        let (initial, rest) = match tokens.first() {
+            Some(&"startpos") => (Ban::new(), &tokens[1..]),
+            Some(&"sfen") if tokens.len() >= 5 => {
+                (Ban::from_sfen(&tokens[1..5].join(" "))?, &tokens[5..])
+            }
+            _ => return Err(Error::Notation(format!("invalid position {}", line))),
+        };
+        let mut record = Self::new(initial);
+        let moves = match rest.first() {
+            Some(&"moves") => &rest[1..],
+            None => rest,
+            _ => return Err(Error::Notation(format!("invalid position {}", line))),
+        };
+        let mut ban = record.initial.clone();
+        for mv in moves {
+            let hand: Hand = (*mv).try_into()?;
+            ban.apply_hand(&hand)?;
+            record.moves.push(MoveRecord::new(hand));
+        }
+        Ok(record)
+    }
+
+    /// Write the position after the main line as a USI `position` command
+    pub fn to_usi(&self) -> String {
+        let mut out = if self.initial == Ban::new() {
+            "position startpos".to_string()
+        } else {
+            format!("position sfen {}", self.initial.to_sfen())
+        };
+        if !self.moves.is_empty() {
+            out.push_str(" moves");
+            for mv in &self.moves {
+                out.push(' ');
+                out.push_str(&String::from(mv.hand.clone()));
+            }
+        }
+        out
+    }
+}
+
+#[test]
+fn game_record() {
+    use std::convert::TryInto;
+
+    let mut record = GameRecord::new(Ban::new());
+    record.set_header(SENTE_NAME, "Alice");
+    record.set_header(GOTE_NAME, "Bob");
+    record.set_header(EVENT, "test");
+    record.set_header(DATE, "2020/01/01");
+    record.set_header(SENTE_NAME, "Carol");
+    assert_eq!(record.sente_name(), Some("Carol"));
+    assert_eq!(record.gote_name(), Some("Bob"));
+    assert_eq!(record.event(), Some("test"));
+    assert_eq!(record.date(), Some("2020/01/01"));
+    assert_eq!(record.headers.len(), 4);
+
+    for (usi, seconds) in [("7g7f", 3), ("3c3d", 5), ("8h2b+", 1)].iter() {
+        let mut mv = MoveRecord::new((*usi).try_into().unwrap());
+        mv.time = Some(Duration::from_secs(*seconds));
+        record.push(mv).unwrap();
+    }
+    assert_eq!(
+        record.push(MoveRecord::new("2b3c".try_into().unwrap())),
+        Err(Error::IllegalMove(
+            crate::error::IllegalMoveKind::NotYourPiece
+        ))
+    );
+    record.moves[2].evaluation = Some(500);
+    record.result = Some(GameResult::Resign);
+    assert!(!record.last_turn());
+    assert_eq!(record.winner(), Some(true));
+
+    assert_eq!(record.ban_at(0), Some(Ban::new()));
+    assert_eq!(
+        record.ban_at(2).unwrap().to_sfen(),
+        "lnsgkgsnl/1r5b1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL b - 3"
+    );
+    assert_eq!(record.ban_at(3), record.last_ban().ok());
+    assert_eq!(record.ban_at(4), None);
+
+    for format in [KifuFormat::Kif, KifuFormat::Ki2, KifuFormat::Csa].iter() {
+        let parsed = GameRecord::parse(&record.to_kifu(*format), *format).unwrap();
+        assert_eq!(parsed.ban_at(3), record.ban_at(3));
+        assert_eq!(parsed.result, record.result);
+        assert_eq!(parsed.sente_name(), Some("Carol"));
+    }
+    let csa = GameRecord::parse(&record.to_kifu(KifuFormat::Csa), KifuFormat::Csa).unwrap();
+    assert_eq!(csa.moves, record.moves);
+    assert_eq!(KifuFormat::from_extension("KIF"), Some(KifuFormat::Kif));
+    assert_eq!(KifuFormat::from_extension("txt"), None);
+}
+
+#[test]
+fn usi_position() {
+    let record = GameRecord::from_usi("position startpos moves 7g7f 3c3d").unwrap();
+    assert_eq!(record.moves.len(), 2);
+    assert_eq!(record.to_usi(), "position startpos moves 7g7f 3c3d");
+    assert_eq!(
+        GameRecord::from_usi("startpos").unwrap().to_usi(),
+        "position startpos"
+    );
+
+    let sfen = "sfen 4k4/9/9/9/9/9/9/9/4K4 b G 1 moves G*5b";
+    let record = GameRecord::from_usi(sfen).unwrap();
+    assert_eq!(record.to_usi(), format!("position {}", sfen));
+
+    assert!(GameRecord::from_usi("").is_err());
+    assert!(GameRecord::from_usi("position startpos 7g7f").is_err());
+    assert!(GameRecord::from_usi("position startpos moves 7g7f 7g7f").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_game_record() {
+    use std::convert::TryInto;
+
+    let mut record = GameRecord::new(Ban::new());
+    record
+        .push(MoveRecord::new("7g7f".try_into().unwrap()))
+        .unwrap();
+    record.result = Some(GameResult::Resign);
+    let json = serde_json::to_string(&record).unwrap();
+    assert!(json.starts_with(
+        r#"{"initial":"lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1","moves":[{"hand":"7g7f","#
+    ));
+    assert_eq!(serde_json::from_str::<GameRecord>(&json).unwrap(), record);
+
+    assert!(serde_json::from_str::<Ban>(r#""9/9 b - 1""#).is_err());
+    assert!(serde_json::from_str::<Hand>(r#""7g7""#).is_err());
+    assert_eq!(
+        serde_json::to_string(&crate::Piece::Kaku).unwrap(),
+        r#""Kaku""#
+    );
+}
@@ -0,0 +1,147 @@
+//! Rules to end a game other than checkmate, i.e. repetition and declaration by entering king
+
+use crate::{ban2::Ban2 as Ban, record::GameResult, Piece};
+
+/// Times the same position appears to end the game by repetition
+const REPETITION: usize = 4;
+
+/// Check the last position of given positions, from the initial one to the one after the
+/// last move, repeated. The result is seen from the turn of the last position, so that
+/// perpetual check by the turn to move is `IllegalMove` and the one by the last mover is
+/// `IllegalWin`
+pub fn repetition(bans: &[Ban]) -> Option<GameResult> {
+    let last = bans.last()?;
+    let same = bans
+        .iter()
+        .enumerate()
+        .filter(|(_, ban)| same_position(ban, last))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if same.len() < REPETITION {
+        return None;
+    }
+    let cycle = &bans[same[same.len() - REPETITION] + 1..];
+    // every move of `side` in the cycle checks its opponent
+    let perpetual_check = |side: bool| {
+        cycle
+            .iter()
+            .filter(|ban| ban.turn != side)
+            .all(|ban| ban.is_check(ban.turn))
+    };
+    let last_mover = !last.turn;
+    Some(if perpetual_check(last_mover) {
+        GameResult::IllegalWin
+    } else if perpetual_check(!last_mover) {
+        GameResult::IllegalMove
+    } else {
+        GameResult::Sennichite
+    })
+}
+
+fn same_position(a: &Ban, b: &Ban) -> bool {
+    let mut b = b.clone();
+    b.ply = a.ply;
+    *a == b
+}
+
+/// Points of given side counted for declaration, i.e. pieces in the enemy camp and in hand
+/// except Ou, 5 for Hisha and Kaku and 1 for others. Also returns the number of pieces in the
+/// enemy camp
+pub fn declaration_points(ban: &Ban, turn: bool) -> (usize, usize) {
+    let point = |piece: Piece| match piece {
+        Piece::Hisha | Piece::Kaku => 5,
+        Piece::Ou => 0,
+        _ => 1,
+    };
+    let camp = if turn { 1..=3 } else { 7..=9 };
+    let mut points = 0;
+    let mut count = 0;
+    for x in 1..=9 {
+        for y in camp.clone() {
+            if let Some(p) = ban.get_position(x, y) {
+                if p.turn == turn && p.piece != Piece::Ou {
+                    points += point(p.piece);
+                    count += 1;
+                }
+            }
+        }
+    }
+    let havings = if turn {
+        &ban.primary_pieces
+    } else {
+        &ban.secondary_pieces
+    };
+    points += havings.iter().map(|p| point(*p)).sum::<usize>();
+    (points, count)
+}
+
+/// Check the turn to move can declare its win by the 27 point rule: its Ou is in the enemy
+/// camp and not checked, it has 10 other pieces there, and 28 points for sente or 27 for gote
+pub fn can_declare(ban: &Ban) -> bool {
+    let turn = ban.turn;
+    let entered = match ban.find_ou(turn) {
+        Some((_, y)) if turn => y <= 3,
+        Some((_, y)) => y >= 7,
+        None => false,
+    };
+    let (points, count) = declaration_points(ban, turn);
+    let required = if turn { 28 } else { 27 };
+    entered && !ban.is_check(turn) && count >= 10 && points >= required
+}
+
+#[test]
+fn repetition_rule() {
+    use std::convert::TryInto;
+
+    let play = |sfen: &str, moves: &[&str]| {
+        let mut ban = Ban::from_sfen(sfen).unwrap();
+        let mut bans = vec![ban.clone()];
+        for mv in moves {
+            ban.apply_hand(&(*mv).try_into().unwrap()).unwrap();
+            bans.push(ban.clone());
+        }
+        bans
+    };
+
+    let shuffle = ["5i5h", "5a5b", "5h5i", "5b5a"];
+    let moves = shuffle.iter().cycle().take(11).copied().collect::<Vec<_>>();
+    let bans = play(crate::START_POS, &moves);
+    assert_eq!(repetition(&bans), None);
+    let moves = shuffle.iter().cycle().take(12).copied().collect::<Vec<_>>();
+    let bans = play(crate::START_POS, &moves);
+    assert_eq!(repetition(&bans), Some(GameResult::Sennichite));
+
+    // sente keeps checking with its rook
+    let sfen = "4k4/9/9/9/9/9/9/9/R3K4 b - 1";
+    let checks = ["9i9a", "5a5b", "9a9b", "5b5a", "9b9a"];
+    let mut moves = checks.to_vec();
+    moves.extend(checks[1..].iter());
+    let bans = play(sfen, &moves);
+    assert_eq!(repetition(&bans), None);
+    moves.extend(checks[1..].iter());
+    let bans = play(sfen, &moves);
+    assert!(!bans.last().unwrap().turn);
+    assert_eq!(repetition(&bans), Some(GameResult::IllegalWin));
+}
+
+#[test]
+fn declaration() {
+    let ban = Ban::from_sfen("LNSGKGSNL/1R5B1/PPPPPPPPP/9/9/9/9/9/4k4 b P 1").unwrap();
+    assert_eq!(declaration_points(&ban, true), (28, 19));
+    assert!(can_declare(&ban));
+
+    // 27 points is enough only for gote
+    let ban = Ban::from_sfen("LNSGKGSNL/1R5B1/PPPPPPPPP/9/9/9/9/9/4k4 b - 1").unwrap();
+    assert!(!can_declare(&ban));
+    let ban = Ban::from_sfen("4K4/9/9/9/9/9/ppppppppp/1b5r1/lnsgkgsnl w - 1").unwrap();
+    assert_eq!(declaration_points(&ban, false), (27, 19));
+    assert!(can_declare(&ban));
+
+    // Ou is not in the enemy camp
+    let ban = Ban::from_sfen("LNSG1GSNL/1R5B1/PPPPPPPPP/4K4/9/9/9/9/4k4 b P 1").unwrap();
+    assert!(!can_declare(&ban));
+    // too few pieces in the enemy camp
+    let ban = Ban::from_sfen("4K4/9/9/9/9/9/9/9/4k4 b 2R2B4G 1").unwrap();
+    assert_eq!(declaration_points(&ban, true), (24, 0));
+    assert!(!can_declare(&ban));
+}
@@ -0,0 +1,268 @@
+//! Thinking time of a move from the clock sent by `go`
+
+use std::time::Duration;
+
+use crate::{usi::GoParams, Hand};
+
+/// Plies a game is expected to last, to spread the remaining time
+const EXPECTED_PLIES: usize = 160;
+/// Own moves the remaining time is kept for even late in a game
+const MIN_MOVES_LEFT: u32 = 16;
+/// Maximum time compared with the optimum one
+const MAX_RATIO: u32 = 5;
+/// Part of the remaining time a single move may take at most
+const MAX_TIME_DIVISOR: u32 = 4;
+/// Limit of the extension of the optimum time
+const MAX_EXTENSION: f64 = 2.5;
+/// Score drops extending the optimum time by a half and a whole
+const SMALL_DROP: isize = 80;
+const LARGE_DROP: isize = 200;
+
+/// Optimum and maximum thinking times of a move, extended while the best move is unstable or
+/// its score drops. It is fed elapsed times instead of reading a clock
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeManager {
+    /// `None` for searches without time limits
+    limits: Option<(Duration, Duration)>,
+    /// Best move changes, halved every iteration
+    instability: f64,
+    score_drop: f64,
+    last: Option<(Hand, isize)>,
+}
+
+impl TimeManager {
+    /// Compute thinking times of the side to move `turn` at `ply` from `params`, keeping
+    /// `margin` of the remaining time and byoyomi for the network delay. `go infinite`,
+    /// `go ponder` and `go` without clocks are not limited
+    pub fn new(params: &GoParams, turn: bool, ply: usize, margin: Duration) -> Self {
+        let time = if turn { params.btime } else { params.wtime };
+        let byoyomi = params.byoyomi.unwrap_or_default();
+        let inc = params
+            .inc
+            .map(|(binc, winc)| if turn { binc } else { winc })
+            .unwrap_or_default();
+        let limits =
+            if params.infinite || params.ponder || time + byoyomi + inc == Duration::default() {
+                None
+            } else {
+                let moves_left =
+                    ((EXPECTED_PLIES.saturating_sub(ply) / 2) as u32).max(MIN_MOVES_LEFT);
+                let base = time / moves_left + inc;
+                // the increment is added after the move, so only byoyomi is on top of the time
+                let hard = (time + byoyomi).saturating_sub(margin);
+                let maximum = (base * MAX_RATIO).min(time / MAX_TIME_DIVISOR + inc) + byoyomi;
+                let maximum = maximum.min(hard);
+                Some(((base + byoyomi).min(maximum), maximum))
+            };
+        Self {
+            limits,
+            instability: 0.0,
+            score_drop: 0.0,
+            last: None,
+        }
+    }
+
+    pub fn optimum(&self) -> Option<Duration> {
+        self.limits.map(|(optimum, _)| optimum)
+    }
+
+    pub fn maximum(&self) -> Option<Duration> {
+        self.limits.map(|(_, maximum)| maximum)
+    }
+
+    /// Optimum time extended by the instability of the search, within the maximum time
+    pub fn extended(&self) -> Option<Duration> {
+        let factor = (1.0 + self.instability * 0.5 + self.score_drop).min(MAX_EXTENSION);
+        self.limits
+            .map(|(optimum, maximum)| optimum.mul_f64(factor).min(maximum))
+    }
+
+    /// Record the result of a finished iteration, with the score from the side to move
+    pub fn report(&mut self, best: &Hand, score: isize) {
+        self.instability *= 0.5;
+        self.score_drop = 0.0;
+        if let Some((last_best, last_score)) = &self.last {
+            if last_best != best {
+                self.instability += 1.0;
+            }
+            let drop = last_score - score;
+            if drop >= LARGE_DROP {
+                self.score_drop = 1.0;
+            } else if drop >= SMALL_DROP {
+                self.score_drop = 0.5;
+            }
+        }
+        self.last = Some((best.clone(), score));
+    }
+
+    /// Check the search should be stopped at `elapsed`
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        self.extended().is_some_and(|limit| elapsed >= limit)
+    }
+
+    /// Check an iteration expected to take `expected` can be started at `elapsed`. One expected
+    /// to overrun the maximum time would be stopped on the way and wasted, so it is not started
+    pub fn can_iterate(&self, elapsed: Duration, expected: Duration) -> bool {
+        match self.maximum() {
+            Some(maximum) => !self.should_stop(elapsed) && elapsed + expected <= maximum,
+            None => true,
+        }
+    }
+}
+
+#[test]
+fn sudden_death() {
+    let ms = Duration::from_millis;
+    let params = GoParams {
+        btime: ms(600_000),
+        wtime: ms(300_000),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, true, 0, ms(100));
+    assert_eq!(tm.optimum(), Some(ms(7_500)));
+    assert_eq!(tm.maximum(), Some(ms(37_500)));
+    let tm = TimeManager::new(&params, false, 1, ms(100));
+    assert_eq!(tm.optimum(), Some(ms(300_000) / 79));
+
+    // late in a game the time is kept for a few more moves
+    let tm = TimeManager::new(&params, true, 300, ms(100));
+    assert_eq!(tm.optimum(), Some(ms(37_500)));
+    assert_eq!(tm.maximum(), Some(ms(150_000)));
+
+    // simulate a game until the clock runs out, spending the optimum time every move
+    let mut remaining = ms(60_000);
+    for ply in (0..400).step_by(2) {
+        let params = GoParams {
+            btime: remaining,
+            ..GoParams::default()
+        };
+        let tm = TimeManager::new(&params, true, ply, ms(100));
+        let spent = tm.optimum().unwrap();
+        assert!(tm.maximum().unwrap() < remaining);
+        remaining -= spent;
+    }
+    assert!(remaining > Duration::default());
+
+    // a clock shorter than the margin leaves no time
+    let params = GoParams {
+        btime: ms(50),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, true, 0, ms(100));
+    assert_eq!(tm.maximum(), Some(Duration::default()));
+    assert!(tm.should_stop(Duration::default()));
+}
+
+#[test]
+fn byoyomi_and_increment() {
+    let ms = Duration::from_millis;
+    // only byoyomi is left: use it all but the margin
+    let params = GoParams {
+        byoyomi: Some(ms(10_000)),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, false, 100, ms(300));
+    assert_eq!(tm.optimum(), Some(ms(9_700)));
+    assert_eq!(tm.maximum(), Some(ms(9_700)));
+
+    let params = GoParams {
+        btime: ms(80_000),
+        byoyomi: Some(ms(10_000)),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, true, 0, ms(300));
+    assert_eq!(tm.optimum(), Some(ms(11_000)));
+    assert_eq!(tm.maximum(), Some(ms(15_000)));
+
+    // the increment comes after the move, so the clock must not be overrun
+    let params = GoParams {
+        btime: ms(1_000),
+        wtime: ms(1_000),
+        inc: Some((ms(5_000), ms(2_000))),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, true, 0, ms(100));
+    assert_eq!(tm.maximum(), Some(ms(900)));
+    assert_eq!(tm.optimum(), Some(ms(900)));
+    let params = GoParams {
+        btime: ms(60_000),
+        wtime: ms(60_000),
+        inc: Some((ms(5_000), ms(2_000))),
+        ..GoParams::default()
+    };
+    let tm = TimeManager::new(&params, false, 0, ms(100));
+    assert_eq!(tm.optimum(), Some(ms(2_750)));
+    assert_eq!(tm.maximum(), Some(ms(13_750)));
+
+    for params in [
+        GoParams::default(),
+        GoParams {
+            infinite: true,
+            ..params
+        },
+        GoParams {
+            ponder: true,
+            ..params
+        },
+    ]
+    .iter()
+    {
+        let tm = TimeManager::new(params, true, 0, ms(100));
+        assert_eq!(tm.maximum(), None);
+        assert!(!tm.should_stop(ms(1_000_000)));
+        assert!(tm.can_iterate(ms(1_000_000), ms(1_000_000)));
+    }
+}
+
+#[test]
+fn extension() {
+    use std::convert::TryInto;
+
+    let ms = Duration::from_millis;
+    let params = GoParams {
+        btime: ms(80_000),
+        ..GoParams::default()
+    };
+    let mut tm = TimeManager::new(&params, true, 0, ms(100));
+    let (a, b): (Hand, Hand) = ("7g7f".try_into().unwrap(), "2g2f".try_into().unwrap());
+    tm.report(&a, 50);
+    tm.report(&a, 40);
+    assert_eq!(tm.extended(), Some(ms(1_000)));
+    assert!(tm.should_stop(ms(1_000)));
+    assert!(tm.can_iterate(ms(500), ms(4_500)));
+    assert!(!tm.can_iterate(ms(500), ms(4_600)));
+
+    // changes of the best move extend the time, fading away when it settles
+    tm.report(&b, 40);
+    assert_eq!(tm.extended(), Some(ms(1_500)));
+    assert!(!tm.should_stop(ms(1_000)));
+    tm.report(&a, 40);
+    assert_eq!(tm.extended(), Some(ms(1_750)));
+    tm.report(&a, 40);
+    tm.report(&a, 40);
+    tm.report(&a, 40);
+    assert!(tm.extended().unwrap() < ms(1_100));
+
+    // so do score drops
+    tm.report(&a, -60);
+    assert!(tm.extended().unwrap() >= ms(1_500));
+    tm.report(&a, -300);
+    assert!(tm.extended().unwrap() >= ms(2_000));
+    tm.report(&a, -300);
+    assert!(tm.extended().unwrap() < ms(1_100));
+
+    // within the maximum time
+    for _ in 0..10 {
+        tm.report(&a, 0);
+        tm.report(&b, -1000);
+    }
+    assert_eq!(tm.extended(), Some(ms(2_500)));
+    let params = GoParams {
+        byoyomi: Some(ms(1_000)),
+        ..GoParams::default()
+    };
+    let mut tm = TimeManager::new(&params, true, 0, ms(100));
+    tm.report(&a, 0);
+    tm.report(&b, -1000);
+    assert_eq!(tm.extended(), Some(ms(900)));
+}
@@ -0,0 +1,737 @@
+//! Messages of the USI protocol between GUIs and engines, parsed from and written as lines
+
+pub mod options;
+
+use std::{convert::TryInto, fmt, time::Duration};
+
+use crate::{error::Error, handicap::Handicap, record::GameRecord, Hand};
+
+/// Messages from GUIs to engines
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Usi,
+    IsReady,
+    SetOption {
+        name: String,
+        /// `None` for buttons
+        value: Option<String>,
+    },
+    UsiNewGame,
+    /// The initial position and the moves from it. Also accepts the non-standard
+    /// `position handicap <name>` where the name is one of `Handicap::from_name`
+    Position(Box<GameRecord>),
+    Go(GoParams),
+    /// Search a checkmate within given time, `None` for `infinite`
+    GoMate(Option<Duration>),
+    Stop,
+    PonderHit,
+    /// Result of the game for the engine, `None` for a draw
+    GameOver(Option<bool>),
+    Quit,
+}
+
+/// Arguments of `go`, times are the remaining ones of each side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GoParams {
+    pub btime: Duration,
+    pub wtime: Duration,
+    pub byoyomi: Option<Duration>,
+    /// Increments of (sente, gote)
+    pub inc: Option<(Duration, Duration)>,
+    pub ponder: bool,
+    pub infinite: bool,
+}
+
+/// Messages from engines to GUIs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    IdName(String),
+    IdAuthor(String),
+    Option(OptionDef),
+    UsiOk,
+    ReadyOk,
+    Info(Info),
+    BestMove(BestMove),
+    Checkmate(Checkmate),
+}
+
+/// An option declared in reply to `usi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionDef {
+    pub name: String,
+    pub kind: OptionKind,
+}
+
+/// Type and default value of an option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionKind {
+    Check(bool),
+    Spin { default: i64, min: i64, max: i64 },
+    Combo { default: String, vars: Vec<String> },
+    Button,
+    String(String),
+    Filename(String),
+}
+
+/// Evaluation in `info score`, seen from the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Cp(isize),
+    /// Mate in given plies, negative when the side to move is mated. `None` for `mate +` or
+    /// `mate -` without the number, with the sign
+    Mate(Option<isize>, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// An `info` line, fields which are not sent are `None` or empty
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Info {
+    pub depth: Option<usize>,
+    pub seldepth: Option<usize>,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub hashfull: Option<usize>,
+    pub multipv: Option<usize>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub currmove: Option<Hand>,
+    pub pv: Vec<Hand>,
+    /// Rest of the line after `string`
+    pub string: Option<String>,
+}
+
+/// Reply to `go`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BestMove {
+    Move {
+        hand: Hand,
+        ponder: Option<Hand>,
+    },
+    Resign,
+    /// Declaration of win by entering king
+    Win,
+}
+
+/// Reply to `go mate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checkmate {
+    Mate(Vec<Hand>),
+    NoMate,
+    Timeout,
+    NotImplemented,
+}
+
+fn usi_error(message: impl Into<String>) -> Error {
+    Error::Usi(message.into())
+}
+
+fn parse_millis(token: Option<&str>) -> Result<Duration, Error> {
+    token
+        .and_then(|t| t.parse().ok())
+        .map(Duration::from_millis)
+        .ok_or_else(|| usi_error(format!("invalid time {}", token.unwrap_or_default())))
+}
+
+impl Command {
+    /// Parse a line, ignoring unknown arguments
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let tokens = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let command = match tokens.first() {
+            Some(command) => *command,
+            None => return Err(usi_error("empty command")),
+        };
+        let args = &tokens[1..];
+        match command {
+            "usi" => Ok(Command::Usi),
+            "isready" => Ok(Command::IsReady),
+            "setoption" => parse_setoption(args),
+            "usinewgame" => Ok(Command::UsiNewGame),
+            "position" => parse_position(args).map(|record| Command::Position(Box::new(record))),
+            "go" if args.first() == Some(&"mate") => match args.get(1) {
+                None | Some(&"infinite") => Ok(Command::GoMate(None)),
+                time => parse_millis(time.copied()).map(|t| Command::GoMate(Some(t))),
+            },
+            "go" => parse_go(args).map(Command::Go),
+            "stop" => Ok(Command::Stop),
+            "ponderhit" => Ok(Command::PonderHit),
+            "gameover" => match args.first() {
+                Some(&"win") => Ok(Command::GameOver(Some(true))),
+                Some(&"lose") => Ok(Command::GameOver(Some(false))),
+                Some(&"draw") => Ok(Command::GameOver(None)),
+                _ => Err(usi_error(format!("invalid gameover {}", line))),
+            },
+            "quit" => Ok(Command::Quit),
+            _ => Err(usi_error(format!("unknown command {}", command))),
+        }
+    }
+}
+
+/// Parse `name <id> [value <x>]`, where the value may have spaces
+fn parse_setoption(args: &[&str]) -> Result<Command, Error> {
+    if args.first() != Some(&"name") {
+        return Err(usi_error("setoption without name"));
+    }
+    let value_at = args.iter().position(|a| *a == "value");
+    let name = args[1..value_at.unwrap_or(args.len())].join(" ");
+    if name.is_empty() {
+        return Err(usi_error("setoption without name"));
+    }
+    Ok(Command::SetOption {
+        name,
+        value: value_at.map(|i| args[i + 1..].join(" ")),
+    })
+}
+
+fn parse_position(args: &[&str]) -> Result<GameRecord, Error> {
+    match args {
+        ["handicap", name, rest @ ..] => {
+            let handicap = Handicap::from_name(name)
+                .ok_or_else(|| usi_error(format!("unknown handicap {}", name)))?;
+            GameRecord::from_usi(&format!("sfen {} {}", handicap.sfen(), rest.join(" ")))
+        }
+        _ => GameRecord::from_usi(&args.join(" ")),
+    }
+}
+
+fn parse_go(args: &[&str]) -> Result<GoParams, Error> {
+    let mut params = GoParams::default();
+    let mut tokens = args.iter().copied();
+    while let Some(token) = tokens.next() {
+        match token {
+            "ponder" => params.ponder = true,
+            "infinite" => params.infinite = true,
+            "btime" => params.btime = parse_millis(tokens.next())?,
+            "wtime" => params.wtime = parse_millis(tokens.next())?,
+            "byoyomi" => params.byoyomi = Some(parse_millis(tokens.next())?),
+            "binc" => {
+                let winc = params.inc.map_or(Duration::ZERO, |(_, w)| w);
+                params.inc = Some((parse_millis(tokens.next())?, winc));
+            }
+            "winc" => {
+                let binc = params.inc.map_or(Duration::ZERO, |(b, _)| b);
+                params.inc = Some((binc, parse_millis(tokens.next())?));
+            }
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Usi => write!(f, "usi"),
+            Command::IsReady => write!(f, "isready"),
+            Command::SetOption { name, value } => {
+                write!(f, "setoption name {}", name)?;
+                match value {
+                    Some(value) => write!(f, " value {}", value),
+                    None => Ok(()),
+                }
+            }
+            Command::UsiNewGame => write!(f, "usinewgame"),
+            Command::Position(record) => write!(f, "{}", record.to_usi()),
+            Command::Go(params) => write!(f, "{}", params),
+            Command::GoMate(None) => write!(f, "go mate infinite"),
+            Command::GoMate(Some(time)) => write!(f, "go mate {}", time.as_millis()),
+            Command::Stop => write!(f, "stop"),
+            Command::PonderHit => write!(f, "ponderhit"),
+            Command::GameOver(result) => write!(
+                f,
+                "gameover {}",
+                match result {
+                    Some(true) => "win",
+                    Some(false) => "lose",
+                    None => "draw",
+                }
+            ),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl fmt::Display for GoParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "go")?;
+        if self.ponder {
+            write!(f, " ponder")?;
+        }
+        if self.infinite {
+            return write!(f, " infinite");
+        }
+        write!(
+            f,
+            " btime {} wtime {}",
+            self.btime.as_millis(),
+            self.wtime.as_millis()
+        )?;
+        if let Some(byoyomi) = self.byoyomi {
+            write!(f, " byoyomi {}", byoyomi.as_millis())?;
+        }
+        if let Some((binc, winc)) = self.inc {
+            write!(f, " binc {} winc {}", binc.as_millis(), winc.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+impl Response {
+    /// Parse a line, ignoring unknown arguments
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        match command {
+            "id" => match rest.split_once(' ') {
+                Some(("name", name)) => Ok(Response::IdName(name.trim().to_string())),
+                Some(("author", author)) => Ok(Response::IdAuthor(author.trim().to_string())),
+                _ => Err(usi_error(format!("invalid id {}", line))),
+            },
+            "option" => OptionDef::parse(rest).map(Response::Option),
+            "usiok" => Ok(Response::UsiOk),
+            "readyok" => Ok(Response::ReadyOk),
+            "info" => Ok(Response::Info(Info::parse(rest))),
+            "bestmove" => BestMove::parse(rest).map(Response::BestMove),
+            "checkmate" => Checkmate::parse(rest).map(Response::Checkmate),
+            "" => Err(usi_error("empty response")),
+            _ => Err(usi_error(format!("unknown response {}", command))),
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Response::IdName(name) => write!(f, "id name {}", name),
+            Response::IdAuthor(author) => write!(f, "id author {}", author),
+            Response::Option(option) => write!(f, "option {}", option),
+            Response::UsiOk => write!(f, "usiok"),
+            Response::ReadyOk => write!(f, "readyok"),
+            Response::Info(info) => write!(f, "info{}", info),
+            Response::BestMove(best) => write!(f, "bestmove {}", best),
+            Response::Checkmate(checkmate) => write!(f, "checkmate {}", checkmate),
+        }
+    }
+}
+
+/// Default values of strings and filenames which are empty
+const EMPTY: &str = "<empty>";
+
+impl OptionDef {
+    /// Parse arguments of `option` like `name USI_Hash type spin default 256 min 1 max 4096`
+    pub fn parse(args: &str) -> Result<Self, Error> {
+        let tokens = args.split_ascii_whitespace().collect::<Vec<_>>();
+        let invalid = || usi_error(format!("invalid option {}", args));
+        // values of the keys, where `var` may appear more than once
+        let mut name = None;
+        let mut kind = None;
+        let mut default = None;
+        let mut min = None;
+        let mut max = None;
+        let mut vars = Vec::new();
+        let mut i = 0;
+        while i + 1 < tokens.len() {
+            let value = tokens[i + 1];
+            match tokens[i] {
+                "name" => name = Some(value),
+                "type" => kind = Some(value),
+                "default" => default = Some(value),
+                "min" => min = value.parse().ok(),
+                "max" => max = value.parse().ok(),
+                "var" => vars.push(value.to_string()),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            i += 2;
+        }
+        let text = |default: Option<&str>| match default {
+            None | Some(EMPTY) => String::new(),
+            Some(s) => s.to_string(),
+        };
+        let kind = match kind.ok_or_else(invalid)? {
+            "check" => OptionKind::Check(default == Some("true")),
+            "spin" => OptionKind::Spin {
+                default: default.and_then(|d| d.parse().ok()).ok_or_else(invalid)?,
+                min: min.ok_or_else(invalid)?,
+                max: max.ok_or_else(invalid)?,
+            },
+            "combo" => OptionKind::Combo {
+                default: text(default),
+                vars,
+            },
+            "button" => OptionKind::Button,
+            "string" => OptionKind::String(text(default)),
+            "filename" => OptionKind::Filename(text(default)),
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            name: name.ok_or_else(invalid)?.to_string(),
+            kind,
+        })
+    }
+}
+
+impl fmt::Display for OptionDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = |s: &str| if s.is_empty() { EMPTY } else { s }.to_string();
+        write!(f, "name {} type ", self.name)?;
+        match &self.kind {
+            OptionKind::Check(default) => write!(f, "check default {}", default),
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {} min {} max {}", default, min, max)
+            }
+            OptionKind::Combo { default, vars } => {
+                write!(f, "combo default {}", text(default))?;
+                vars.iter().try_for_each(|var| write!(f, " var {}", var))
+            }
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::String(default) => write!(f, "string default {}", text(default)),
+            OptionKind::Filename(default) => write!(f, "filename default {}", text(default)),
+        }
+    }
+}
+
+impl Info {
+    /// Parse arguments of `info`, skipping unknown or malformed fields
+    pub fn parse(args: &str) -> Self {
+        let mut info = Info::default();
+        let mut tokens = args.split_ascii_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = tokens.next().and_then(|t| t.parse().ok()),
+                "seldepth" => info.seldepth = tokens.next().and_then(|t| t.parse().ok()),
+                "time" => {
+                    info.time = tokens
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .map(Duration::from_millis)
+                }
+                "nodes" => info.nodes = tokens.next().and_then(|t| t.parse().ok()),
+                "nps" => info.nps = tokens.next().and_then(|t| t.parse().ok()),
+                "hashfull" => info.hashfull = tokens.next().and_then(|t| t.parse().ok()),
+                "multipv" => info.multipv = tokens.next().and_then(|t| t.parse().ok()),
+                "currmove" => info.currmove = tokens.next().and_then(|t| t.try_into().ok()),
+                "score" => {
+                    info.score = match (tokens.next(), tokens.next()) {
+                        (Some("cp"), Some(cp)) => cp.parse().ok().map(Score::Cp),
+                        (Some("mate"), Some("+")) => Some(Score::Mate(None, true)),
+                        (Some("mate"), Some("-")) => Some(Score::Mate(None, false)),
+                        (Some("mate"), Some(plies)) => plies
+                            .parse::<isize>()
+                            .ok()
+                            .map(|n| Score::Mate(Some(n), !plies.starts_with('-'))),
+                        _ => None,
+                    }
+                }
+                "lowerbound" => info.bound = Bound::Lower,
+                "upperbound" => info.bound = Bound::Upper,
+                "pv" => {
+                    // the pv lasts until the end of the line
+                    info.pv = tokens.by_ref().map_while(|t| t.try_into().ok()).collect();
+                }
+                "string" => {
+                    let rest = tokens.collect::<Vec<_>>().join(" ");
+                    info.string = Some(rest);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// Written with a leading space, so that `info` is prepended
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numbers = [
+            ("depth", self.depth.map(|n| n as u64)),
+            ("seldepth", self.seldepth.map(|n| n as u64)),
+            ("time", self.time.map(|t| t.as_millis() as u64)),
+            ("nodes", self.nodes),
+            ("nps", self.nps),
+            ("hashfull", self.hashfull.map(|n| n as u64)),
+            ("multipv", self.multipv.map(|n| n as u64)),
+        ];
+        for (key, value) in numbers.iter() {
+            if let Some(value) = value {
+                write!(f, " {} {}", key, value)?;
+            }
+        }
+        match self.score {
+            Some(Score::Cp(cp)) => write!(f, " score cp {}", cp)?,
+            Some(Score::Mate(Some(plies), _)) => write!(f, " score mate {}", plies)?,
+            Some(Score::Mate(None, true)) => write!(f, " score mate +")?,
+            Some(Score::Mate(None, false)) => write!(f, " score mate -")?,
+            None => {}
+        }
+        match self.bound {
+            Bound::Lower => write!(f, " lowerbound")?,
+            Bound::Upper => write!(f, " upperbound")?,
+            Bound::Exact => {}
+        }
+        if let Some(hand) = &self.currmove {
+            write!(f, " currmove {}", String::from(hand.clone()))?;
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv")?;
+            for hand in &self.pv {
+                write!(f, " {}", String::from(hand.clone()))?;
+            }
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {}", string)?;
+        }
+        Ok(())
+    }
+}
+
+impl BestMove {
+    /// Parse arguments of `bestmove`
+    pub fn parse(args: &str) -> Result<Self, Error> {
+        let tokens = args.split_ascii_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["resign", ..] => Ok(BestMove::Resign),
+            ["win", ..] => Ok(BestMove::Win),
+            [mv, rest @ ..] => {
+                let ponder = match rest {
+                    ["ponder", ponder, ..] => (*ponder).try_into().ok(),
+                    _ => None,
+                };
+                Ok(BestMove::Move {
+                    hand: (*mv).try_into()?,
+                    ponder,
+                })
+            }
+            [] => Err(usi_error("bestmove without move")),
+        }
+    }
+}
+
+impl fmt::Display for BestMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BestMove::Move { hand, ponder } => {
+                write!(f, "{}", String::from(hand.clone()))?;
+                match ponder {
+                    Some(ponder) => write!(f, " ponder {}", String::from(ponder.clone())),
+                    None => Ok(()),
+                }
+            }
+            BestMove::Resign => write!(f, "resign"),
+            BestMove::Win => write!(f, "win"),
+        }
+    }
+}
+
+impl Checkmate {
+    /// Parse arguments of `checkmate`
+    pub fn parse(args: &str) -> Result<Self, Error> {
+        let tokens = args.split_ascii_whitespace().collect::<Vec<_>>();
+        match tokens.as_slice() {
+            ["nomate"] => Ok(Checkmate::NoMate),
+            ["timeout"] => Ok(Checkmate::Timeout),
+            ["notimplemented"] => Ok(Checkmate::NotImplemented),
+            [] => Err(usi_error("checkmate without moves")),
+            moves => Ok(Checkmate::Mate(
+                moves
+                    .iter()
+                    .map(|mv| (*mv).try_into())
+                    .collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Checkmate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Checkmate::Mate(hands) => {
+                let moves = hands
+                    .iter()
+                    .map(|hand| String::from(hand.clone()))
+                    .collect::<Vec<_>>();
+                write!(f, "{}", moves.join(" "))
+            }
+            Checkmate::NoMate => write!(f, "nomate"),
+            Checkmate::Timeout => write!(f, "timeout"),
+            Checkmate::NotImplemented => write!(f, "notimplemented"),
+        }
+    }
+}
+
+#[test]
+fn usi_commands() {
+    let lines = [
+        "usi",
+        "isready",
+        "setoption name USI_Hash value 256",
+        "setoption name BookFile value my books/main.db",
+        "setoption name Clear Hash",
+        "usinewgame",
+        "position startpos",
+        "position startpos moves 7g7f 3c3d",
+        "position sfen 4k4/9/9/9/9/9/9/9/4K4 b G 1 moves G*5b",
+        "go btime 60000 wtime 50000 byoyomi 10000",
+        "go ponder btime 0 wtime 0 binc 2000 winc 3000",
+        "go infinite",
+        "go mate 1000",
+        "go mate infinite",
+        "stop",
+        "ponderhit",
+        "gameover lose",
+        "quit",
+    ];
+    for line in lines.iter() {
+        let command = Command::parse(line).unwrap();
+        assert_eq!(command.to_string(), *line);
+    }
+
+    assert_eq!(
+        Command::parse("setoption name Clear Hash").unwrap(),
+        Command::SetOption {
+            name: "Clear Hash".to_string(),
+            value: None
+        }
+    );
+    assert_eq!(
+        Command::parse("  go  byoyomi 1000 nodes 5  ").unwrap(),
+        Command::Go(GoParams {
+            byoyomi: Some(Duration::from_secs(1)),
+            ..GoParams::default()
+        })
+    );
+    assert_eq!(
+        Command::parse("go winc 500").unwrap(),
+        Command::Go(GoParams {
+            inc: Some((Duration::ZERO, Duration::from_millis(500))),
+            ..GoParams::default()
+        })
+    );
+    assert_eq!(Command::parse("go mate").unwrap(), Command::GoMate(None));
+    match Command::parse("position handicap 2-piece moves 5a4b").unwrap() {
+        Command::Position(record) => {
+            assert_eq!(record.initial, Handicap::TwoPieces.ban());
+            assert_eq!(record.moves.len(), 1);
+        }
+        command => panic!("{:?}", command),
+    }
+
+    for line in [
+        "",
+        "   ",
+        "unknown",
+        "setoption",
+        "setoption name",
+        "position",
+        "position sfen 9/9 b",
+        "position startpos moves 7g7f 7g7f",
+        "position handicap none",
+        "go btime",
+        "go byoyomi soon",
+        "go mate later",
+        "gameover",
+    ]
+    .iter()
+    {
+        assert!(Command::parse(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn usi_responses() {
+    let lines = [
+        "id name Shoshodamon",
+        "id author MysteryJump",
+        "option name USI_Hash type spin default 256 min 1 max 4096",
+        "option name USI_Ponder type check default false",
+        "option name Style type combo default Normal var Solid var Normal var Risky",
+        "option name Clear_Hash type button",
+        "option name BookFile type filename default <empty>",
+        "option name Comment type string default hello",
+        "usiok",
+        "readyok",
+        "info depth 3 seldepth 5 time 120 nodes 4000 nps 33333 hashfull 12 multipv 1 score cp -35 upperbound pv 7g7f 3c3d 2g2f",
+        "info score mate -4 pv 5a4b",
+        "info score mate + string found a mate",
+        "info currmove 7g7f",
+        "bestmove 7g7f ponder 3c3d",
+        "bestmove resign",
+        "bestmove win",
+        "checkmate G*5b",
+        "checkmate nomate",
+        "checkmate timeout",
+        "checkmate notimplemented",
+    ];
+    for line in lines.iter() {
+        let response = Response::parse(line).unwrap();
+        assert_eq!(response.to_string(), *line);
+    }
+
+    match Response::parse(
+        "info depth 3 seldepth 5 time 120 nodes 4000 score cp -35 upperbound pv 7g7f 3c3d",
+    )
+    .unwrap()
+    {
+        Response::Info(info) => {
+            assert_eq!(info.depth, Some(3));
+            assert_eq!(info.time, Some(Duration::from_millis(120)));
+            assert_eq!(info.score, Some(Score::Cp(-35)));
+            assert_eq!(info.bound, Bound::Upper);
+            assert_eq!(info.pv.len(), 2);
+        }
+        response => panic!("{:?}", response),
+    }
+    assert_eq!(
+        Response::parse("info score mate -4").unwrap(),
+        Response::Info(Info {
+            score: Some(Score::Mate(Some(-4), false)),
+            ..Info::default()
+        })
+    );
+    assert_eq!(Info::parse("depth x nodes 5").nodes, Some(5));
+    assert_eq!(
+        Response::parse("option name USI_Hash type spin default 256 min 1 max 4096").unwrap(),
+        Response::Option(OptionDef {
+            name: "USI_Hash".to_string(),
+            kind: OptionKind::Spin {
+                default: 256,
+                min: 1,
+                max: 4096
+            }
+        })
+    );
+    assert_eq!(
+        Response::parse("option name BookFile type filename default <empty>").unwrap(),
+        Response::Option(OptionDef {
+            name: "BookFile".to_string(),
+            kind: OptionKind::Filename(String::new())
+        })
+    );
+
+    for line in [
+        "",
+        "unknown",
+        "id",
+        "option name Hash",
+        "option name Hash type spin default 1",
+        "bestmove",
+        "bestmove 7z7f",
+        "checkmate",
+        "checkmate G*5z",
+    ]
+    .iter()
+    {
+        assert!(Response::parse(line).is_err(), "{}", line);
+    }
+}
@@ -0,0 +1,243 @@
+//! Registry of the options an engine declares in reply to `usi` and receives by `setoption`
+
+use super::{usi_error, OptionDef, OptionKind, EMPTY};
+use crate::error::Error;
+
+/// Current value of an option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    /// Value of a combo, a string or a filename
+    Text(String),
+    /// Whether the button was pressed since it was last taken
+    Button(bool),
+}
+
+impl OptionValue {
+    fn default_of(kind: &OptionKind) -> Self {
+        match kind {
+            OptionKind::Check(default) => OptionValue::Check(*default),
+            OptionKind::Spin { default, .. } => OptionValue::Spin(*default),
+            OptionKind::Combo { default, .. }
+            | OptionKind::String(default)
+            | OptionKind::Filename(default) => OptionValue::Text(default.clone()),
+            OptionKind::Button => OptionValue::Button(false),
+        }
+    }
+}
+
+/// Declared options with their values. Names are matched ignoring ASCII case like most
+/// engines do
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UsiOptions {
+    options: Vec<(OptionDef, OptionValue)>,
+}
+
+impl UsiOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare an option with its default value, replacing the one of the same name
+    pub fn declare(mut self, name: &str, kind: OptionKind) -> Self {
+        let value = OptionValue::default_of(&kind);
+        let def = OptionDef {
+            name: name.to_string(),
+            kind,
+        };
+        match self.find_mut(name) {
+            Some(option) => *option = (def, value),
+            None => self.options.push((def, value)),
+        }
+        self
+    }
+
+    pub fn spin(self, name: &str, default: i64, min: i64, max: i64) -> Self {
+        self.declare(name, OptionKind::Spin { default, min, max })
+    }
+
+    pub fn check(self, name: &str, default: bool) -> Self {
+        self.declare(name, OptionKind::Check(default))
+    }
+
+    pub fn combo(self, name: &str, default: &str, vars: &[&str]) -> Self {
+        let vars = vars.iter().map(|var| var.to_string()).collect();
+        self.declare(
+            name,
+            OptionKind::Combo {
+                default: default.to_string(),
+                vars,
+            },
+        )
+    }
+
+    pub fn string(self, name: &str, default: &str) -> Self {
+        self.declare(name, OptionKind::String(default.to_string()))
+    }
+
+    pub fn filename(self, name: &str, default: &str) -> Self {
+        self.declare(name, OptionKind::Filename(default.to_string()))
+    }
+
+    pub fn button(self, name: &str) -> Self {
+        self.declare(name, OptionKind::Button)
+    }
+
+    /// Declarations in their order, to be sent as `option` lines
+    pub fn defs(&self) -> impl Iterator<Item = &OptionDef> {
+        self.options.iter().map(|(def, _)| def)
+    }
+
+    fn find(&self, name: &str) -> Option<&(OptionDef, OptionValue)> {
+        self.options
+            .iter()
+            .find(|(def, _)| def.name.eq_ignore_ascii_case(name))
+    }
+
+    fn find_mut(&mut self, name: &str) -> Option<&mut (OptionDef, OptionValue)> {
+        self.options
+            .iter_mut()
+            .find(|(def, _)| def.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Validate and apply `setoption name <name> value <value>`, where `value` is `None` for
+    /// buttons. The current value is kept on errors. Options reserved by USI like `USI_Hash`
+    /// are ignored unless declared, since GUIs send them to every engine
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), Error> {
+        let (def, current) = match self.find_mut(name) {
+            Some(option) => option,
+            None if is_reserved(name) => return Ok(()),
+            None => return Err(usi_error(format!("unknown option {}", name))),
+        };
+        let invalid = || {
+            usi_error(format!(
+                "invalid value {} for {}",
+                value.unwrap_or_default(),
+                def.name
+            ))
+        };
+        let value = value.map(str::trim);
+        *current = match (&def.kind, value) {
+            (OptionKind::Button, _) => OptionValue::Button(true),
+            (_, None) => return Err(invalid()),
+            (OptionKind::Check(_), Some(v)) => match v {
+                "true" => OptionValue::Check(true),
+                "false" => OptionValue::Check(false),
+                _ => return Err(invalid()),
+            },
+            (OptionKind::Spin { min, max, .. }, Some(v)) => match v.parse::<i64>() {
+                Ok(n) if (*min..=*max).contains(&n) => OptionValue::Spin(n),
+                _ => return Err(invalid()),
+            },
+            (OptionKind::Combo { vars, .. }, Some(v)) => match vars.iter().find(|var| *var == v) {
+                Some(var) => OptionValue::Text(var.clone()),
+                None => return Err(invalid()),
+            },
+            (OptionKind::String(_), Some(v)) | (OptionKind::Filename(_), Some(v)) => {
+                OptionValue::Text(if v == EMPTY { "" } else { v }.to_string())
+            }
+        };
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.find(name).map(|(_, value)| value)
+    }
+
+    /// Get the value of a check, `false` when no check has given name
+    pub fn get_check(&self, name: &str) -> bool {
+        matches!(self.get(name), Some(OptionValue::Check(true)))
+    }
+
+    pub fn get_spin(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(OptionValue::Spin(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a combo, a string or a filename, which is empty for `<empty>`
+    pub fn get_text(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(OptionValue::Text(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Check the button was pressed, and release it
+    pub fn take_button(&mut self, name: &str) -> bool {
+        match self.find_mut(name) {
+            Some((_, OptionValue::Button(pressed))) => std::mem::replace(pressed, false),
+            _ => false,
+        }
+    }
+}
+
+/// Check given option name has the `USI_` prefix reserved by the protocol
+fn is_reserved(name: &str) -> bool {
+    name.get(..4)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("USI_"))
+}
+
+#[test]
+fn usi_options() {
+    let mut options = UsiOptions::new()
+        .spin("USI_Hash", 256, 1, 4096)
+        .check("USI_Ponder", false)
+        .combo("Style", "Normal", &["Solid", "Normal", "Risky"])
+        .string("Comment", "")
+        .filename("BookFile", "book.db")
+        .button("Clear Hash");
+    let lines = options
+        .defs()
+        .map(|def| format!("option {}", def))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "option name USI_Hash type spin default 256 min 1 max 4096",
+            "option name USI_Ponder type check default false",
+            "option name Style type combo default Normal var Solid var Normal var Risky",
+            "option name Comment type string default <empty>",
+            "option name BookFile type filename default book.db",
+            "option name Clear Hash type button",
+        ]
+    );
+
+    assert_eq!(options.get_spin("USI_Hash"), Some(256));
+    options.set("usi_hash", Some("1024")).unwrap();
+    assert_eq!(options.get_spin("USI_Hash"), Some(1024));
+    assert!(options.set("USI_Hash", Some("0")).is_err());
+    assert!(options.set("USI_Hash", Some("lots")).is_err());
+    assert!(options.set("USI_Hash", None).is_err());
+    assert_eq!(options.get_spin("USI_Hash"), Some(1024));
+
+    options.set("USI_Ponder", Some("true")).unwrap();
+    assert!(options.get_check("USI_Ponder"));
+    assert!(options.set("USI_Ponder", Some("yes")).is_err());
+
+    options.set("Style", Some("Risky")).unwrap();
+    assert_eq!(options.get_text("Style"), Some("Risky"));
+    assert!(options.set("Style", Some("risky")).is_err());
+
+    options.set("BookFile", Some("<empty>")).unwrap();
+    assert_eq!(options.get_text("BookFile"), Some(""));
+    options.set("Comment", Some("with spaces")).unwrap();
+    assert_eq!(options.get_text("Comment"), Some("with spaces"));
+
+    assert!(!options.take_button("Clear Hash"));
+    options.set("Clear Hash", None).unwrap();
+    assert!(options.take_button("Clear Hash"));
+    assert!(!options.take_button("Clear Hash"));
+
+    assert!(options.set("Unknown", Some("1")).is_err());
+    options.set("USI_OwnBook", Some("true")).unwrap();
+    assert_eq!(options.get("USI_OwnBook"), None);
+    assert_eq!(options.get_spin("Style"), None);
+    assert!(!options.get_check("Unknown"));
+
+    let options = options.spin("USI_Hash", 16, 1, 64);
+    assert_eq!(options.defs().count(), 6);
+    assert_eq!(options.get_spin("USI_Hash"), Some(16));
+}
@@ -0,0 +1,243 @@
+//! Controller of USI engines running as child processes, i.e. the GUI side of USI
+
+use std::{
+    ffi::OsStr,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Error,
+    record::GameRecord,
+    usi::{BestMove, Checkmate, Command as UsiCommand, GoParams, Info, OptionDef, Response, Score},
+};
+
+/// Time to wait for `usiok` and `readyok`
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time to wait for `bestmove` or `checkmate` after `stop`
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Result of a search, with the `info` lines sent during it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best: BestMove,
+    pub infos: Vec<Info>,
+}
+
+impl SearchResult {
+    /// Get the last info having a score, which is usually the one of the best move
+    pub fn last_score(&self) -> Option<Score> {
+        self.infos.iter().rev().find_map(|info| info.score)
+    }
+}
+
+/// A USI engine launched as a child process
+pub struct UsiEngine {
+    name: String,
+    author: String,
+    /// Options declared in the handshake
+    options: Vec<OptionDef>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UsiEngine {
+    /// Launch given executable and perform the `usi` handshake
+    pub fn spawn<S: AsRef<OsStr>>(path: S, args: &[S]) -> Result<Self, Error> {
+        let mut child = Command::new(&path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            name: path.as_ref().to_string_lossy().into_owned(),
+            author: String::new(),
+            options: Vec::new(),
+            child,
+            stdin,
+            lines,
+        };
+
+        engine.command(&UsiCommand::Usi)?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            match engine.receive_response(deadline)? {
+                Response::IdName(name) => engine.name = name,
+                Response::IdAuthor(author) => engine.author = author,
+                Response::Option(option) => engine.options.push(option),
+                Response::UsiOk => return Ok(engine),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Options declared in the handshake
+    pub fn options(&self) -> &[OptionDef] {
+        &self.options
+    }
+
+    /// Send a line as is
+    pub fn send(&mut self, line: &str) -> Result<(), Error> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    pub fn command(&mut self, command: &UsiCommand) -> Result<(), Error> {
+        self.send(&command.to_string())
+    }
+
+    /// Receive a line until `deadline`
+    pub fn receive(&mut self, deadline: Instant) -> Result<String, Error> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(Error::Usi(format!("{} did not answer in time", self.name)))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Usi(format!("{} exited", self.name))),
+        }
+    }
+
+    /// Receive a response until `deadline`, skipping lines which are not USI. Malformed
+    /// `bestmove` and `checkmate` are errors since their senders are waiting for them
+    pub fn receive_response(&mut self, deadline: Instant) -> Result<Response, Error> {
+        loop {
+            let line = self.receive(deadline)?;
+            match Response::parse(&line) {
+                Ok(response) => return Ok(response),
+                Err(e) if line.starts_with("bestmove") || line.starts_with("checkmate") => {
+                    return Err(e)
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        self.command(&UsiCommand::SetOption {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+        })
+    }
+
+    /// Send `isready` and wait for `readyok`, skipping other lines like a late `bestmove`
+    pub fn is_ready(&mut self) -> Result<(), Error> {
+        self.command(&UsiCommand::IsReady)?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while Response::parse(&self.receive(deadline)?).ok() != Some(Response::ReadyOk) {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> Result<(), Error> {
+        self.command(&UsiCommand::UsiNewGame)
+    }
+
+    /// Send the position after the main line of given record
+    pub fn position(&mut self, record: &GameRecord) -> Result<(), Error> {
+        self.command(&UsiCommand::Position(Box::new(record.clone())))
+    }
+
+    /// Search the current position and wait for `bestmove` until `timeout`. When it timed
+    /// out, `stop` is sent and `None` is returned
+    pub fn go(
+        &mut self,
+        params: &GoParams,
+        timeout: Duration,
+    ) -> Result<Option<SearchResult>, Error> {
+        self.command(&UsiCommand::Go(*params))?;
+        let deadline = Instant::now() + timeout;
+        match self.wait_bestmove(deadline) {
+            Ok(result) => Ok(Some(result)),
+            Err(_) if Instant::now() >= deadline => {
+                // drain the late bestmove so that it is not taken as the next one
+                self.stop()?;
+                let _ = self.wait_bestmove(Instant::now() + STOP_TIMEOUT);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive lines until `bestmove`, collecting `info` lines
+    pub fn wait_bestmove(&mut self, deadline: Instant) -> Result<SearchResult, Error> {
+        let mut infos = Vec::new();
+        loop {
+            match self.receive_response(deadline)? {
+                Response::BestMove(best) => return Ok(SearchResult { best, infos }),
+                Response::Info(info) => infos.push(info),
+                _ => {}
+            }
+        }
+    }
+
+    /// Search a checkmate of the current position, with the time limit sent to the engine
+    pub fn go_mate(&mut self, limit: Option<Duration>) -> Result<Checkmate, Error> {
+        let deadline = match limit {
+            Some(limit) => Instant::now() + limit + STOP_TIMEOUT,
+            None => far_future(),
+        };
+        self.command(&UsiCommand::GoMate(limit))?;
+        loop {
+            if let Response::Checkmate(checkmate) = self.receive_response(deadline)? {
+                return Ok(checkmate);
+            }
+        }
+    }
+
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.command(&UsiCommand::Stop)
+    }
+
+    pub fn ponderhit(&mut self) -> Result<(), Error> {
+        self.command(&UsiCommand::PonderHit)
+    }
+
+    /// Send `gameover`, `None` for a draw
+    pub fn game_over(&mut self, win: Option<bool>) -> Result<(), Error> {
+        self.command(&UsiCommand::GameOver(win))
+    }
+}
+
+/// Deadline which is never reached in practice
+fn far_future() -> Instant {
+    Instant::now() + Duration::from_secs(60 * 60 * 24 * 365)
+}
+
+impl Drop for UsiEngine {
+    /// Send `quit` and kill the engine if it does not exit soon
+    fn drop(&mut self) {
+        let _ = self.command(&UsiCommand::Quit);
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}